@@ -0,0 +1,333 @@
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+use tempfile::NamedTempFile;
+
+use crate::{
+    download::{sanitize_filename, url_hash},
+    error::Result,
+    Assignment, AssignmentDetails, AssignmentType, Attachment,
+};
+
+/// The last known state of the portal for one student, kept on disk so the app works offline.
+///
+/// Each student has a folder of their own in the cache directory, laid out as:
+///
+/// - `assignments.json`: every assignment seen so far, keyed by ID
+/// - `listings/{C,H}.json`: IDs of the last listing of each type, in portal order
+/// - `details/{id}.json`: details of an assignment
+/// - `history/{id}.json`: every version of the details seen so far, see [`DetailsVersion`]
+/// - `attachments/{url hash}/{name}`: downloaded attachment files, apart by URL since names
+///   repeat across assignments
+/// - `sync/{frontend}.json`: the [`crate::SyncState`] of each frontend, so what one has shown
+///   is still new to the other
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+/// A cached value along with when it was fetched from the portal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cached<T> {
+    pub synced_at: DateTime<Utc>,
+    pub value: T,
+}
+
+/// One version of an assignment's details, as the portal showed it at `synced_at`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DetailsVersion {
+    /// [`AssignmentDetails::content_hash`] of this version.
+    pub hash: String,
+    pub synced_at: DateTime<Utc>,
+    pub details: AssignmentDetails,
+}
+
+impl<T> Cached<T> {
+    pub fn now(value: T) -> Self {
+        Self {
+            synced_at: Utc::now(),
+            value,
+        }
+    }
+}
+
+impl Cache {
+    /// Opens the cache of `student` (see [`crate::Session::student`]) under `root`.
+    pub fn open(root: impl AsRef<Path>, student: &str) -> Result<Self> {
+        let dir = root.as_ref().join(file_name(student));
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The last listing of `type_`, if it was ever fetched.
+    pub fn listing(&self, type_: AssignmentType) -> Result<Option<Cached<Vec<Assignment>>>> {
+        let Some(ids) = read_json::<Cached<Vec<String>>>(&self.listing_path(type_))? else {
+            return Ok(None);
+        };
+        let mut assignments = self.assignments()?;
+        let listing = ids
+            .value
+            .iter()
+            .filter_map(|id| assignments.remove(id))
+            .collect();
+        Ok(Some(Cached {
+            synced_at: ids.synced_at,
+            value: listing,
+        }))
+    }
+
+    /// Saves a complete, unfiltered listing of `type_`.
+    pub fn store_listing(&self, type_: AssignmentType, listing: &[Assignment]) -> Result<()> {
+        let mut assignments = self.assignments()?;
+        for assignment in listing {
+            assignments.insert(assignment.id.clone(), assignment.clone());
+        }
+        write_json(&self.dir.join("assignments.json"), &assignments)?;
+        let ids = listing
+            .iter()
+            .map(|assignment| assignment.id.clone())
+            .collect::<Vec<_>>();
+        write_json(&self.listing_path(type_), &Cached::now(ids))
+    }
+
+    /// Every assignment seen so far, keyed by ID.
+    pub fn assignments(&self) -> Result<BTreeMap<String, Assignment>> {
+        Ok(read_json(&self.dir.join("assignments.json"))?.unwrap_or_default())
+    }
+
+    pub fn details(&self, id: &str) -> Result<Option<Cached<AssignmentDetails>>> {
+        read_json(&self.details_path(id))
+    }
+
+    /// Saves the latest details, keeping the previous version in the history if the content
+    /// changed. Returns `true` if this is a revision of details seen before.
+    pub fn store_details(&self, details: &AssignmentDetails) -> Result<bool> {
+        let mut history = self.history(&details.id)?;
+        if history.is_empty() {
+            // Details cached before versions were kept.
+            if let Some(cached) = self.details(&details.id)? {
+                history.push(DetailsVersion {
+                    hash: cached.value.content_hash(),
+                    synced_at: cached.synced_at,
+                    details: cached.value,
+                });
+            }
+        }
+
+        let hash = details.content_hash();
+        let revised = history.last().is_some_and(|last| last.hash != hash);
+        if history.last().is_none_or(|last| last.hash != hash) {
+            history.push(DetailsVersion {
+                hash,
+                synced_at: Utc::now(),
+                details: details.clone(),
+            });
+            write_json(&self.history_path(&details.id), &history)?;
+        }
+        write_json(&self.details_path(&details.id), &Cached::now(details))?;
+        Ok(revised)
+    }
+
+    /// Every version of the details of assignment `id`, oldest first.
+    pub fn history(&self, id: &str) -> Result<Vec<DetailsVersion>> {
+        Ok(read_json(&self.history_path(id))?.unwrap_or_default())
+    }
+
+    /// Where `attachment` is kept once downloaded.
+    pub fn attachment_path(&self, attachment: &Attachment) -> PathBuf {
+        self.dir
+            .join("attachments")
+            .join(url_hash(&attachment.url))
+            .join(sanitize_filename(&attachment.name))
+    }
+
+    /// The downloaded copy of `attachment`, if there is one.
+    pub fn attachment(&self, attachment: &Attachment) -> Option<PathBuf> {
+        let path = self.attachment_path(attachment);
+        path.is_file().then_some(path)
+    }
+
+    /// Where the [`crate::SyncState`] of `frontend` is kept.
+    pub fn sync_state_path(&self, frontend: &str) -> PathBuf {
+        self.dir
+            .join("sync")
+            .join(format!("{}.json", file_name(frontend)))
+    }
+
+    fn listing_path(&self, type_: AssignmentType) -> PathBuf {
+        self.dir.join("listings").join(format!("{type_}.json"))
+    }
+
+    fn history_path(&self, id: &str) -> PathBuf {
+        self.dir
+            .join("history")
+            .join(format!("{}.json", file_name(id)))
+    }
+
+    fn details_path(&self, id: &str) -> PathBuf {
+        self.dir
+            .join("details")
+            .join(format!("{}.json", file_name(id)))
+    }
+}
+
+/// Keeps only the last path component, so names from the portal can't escape the cache.
+fn file_name(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    match name {
+        "" | "." | ".." => "_".to_string(),
+        name => name.to_string(),
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    Ok(Some(serde_json::from_str(&contents)?))
+}
+
+fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<()> {
+    write_atomic(path, serde_json::to_string(value)?.as_bytes())
+}
+
+/// Writes through a temporary file and renames it, so readers never see half a file.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(parent)?;
+    let mut temp = NamedTempFile::new_in(parent)?;
+    temp.write_all(contents)?;
+    temp.persist(path).map_err(|err| err.error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assignment(id: &str) -> Assignment {
+        Assignment {
+            id: id.to_string(),
+            name: format!("Assignment {id}"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_students_have_separate_caches() {
+        let root = tempfile::tempdir().unwrap();
+        let first = Cache::open(root.path(), "first").unwrap();
+        let second = Cache::open(root.path(), "second").unwrap();
+        first
+            .store_listing(AssignmentType::Homework, &[assignment("1")])
+            .unwrap();
+        assert_eq!(
+            first
+                .listing(AssignmentType::Homework)
+                .unwrap()
+                .unwrap()
+                .value,
+            [assignment("1")]
+        );
+        assert!(second.listing(AssignmentType::Homework).unwrap().is_none());
+        assert_ne!(first.sync_state_path("tui"), second.sync_state_path("tui"));
+        assert_ne!(first.sync_state_path("tui"), first.sync_state_path("cli"));
+    }
+
+    #[test]
+    fn test_store_details_keeps_revisions() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = Cache::open(root.path(), "student").unwrap();
+        let mut details = AssignmentDetails {
+            id: "1".to_string(),
+            title: "Excursion".to_string(),
+            body_html: "<p>Date: 18/04/2025</p>".to_string(),
+            ..Default::default()
+        };
+        assert!(!cache.store_details(&details).unwrap());
+        assert!(!cache.store_details(&details).unwrap());
+        assert_eq!(cache.history("1").unwrap().len(), 1);
+
+        let first = details.clone();
+        details.body_html = "<p>Date: 25/04/2025</p>".to_string();
+        assert!(cache.store_details(&details).unwrap());
+        let history = cache.history("1").unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .map(|version| &version.details)
+                .collect::<Vec<_>>(),
+            [&first, &details]
+        );
+        assert_eq!(history[1].hash, details.content_hash());
+        assert_eq!(cache.details("1").unwrap().unwrap().value, details);
+        assert!(cache.history("2").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_store_details_upgrades_details_cached_without_history() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = Cache::open(root.path(), "student").unwrap();
+        let old = AssignmentDetails {
+            id: "1".to_string(),
+            body_html: "<p>Old</p>".to_string(),
+            ..Default::default()
+        };
+        write_json(&cache.details_path("1"), &Cached::now(&old)).unwrap();
+        let new = AssignmentDetails {
+            body_html: "<p>New</p>".to_string(),
+            ..old.clone()
+        };
+        assert!(cache.store_details(&new).unwrap());
+        assert_eq!(cache.history("1").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_attachments_with_the_same_name_are_kept_apart() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = Cache::open(root.path(), "student").unwrap();
+        let first = Attachment {
+            name: "worksheet.pdf".to_string(),
+            url: "https://portal/Uploads/101/worksheet.pdf".to_string(),
+        };
+        let second = Attachment {
+            url: "https://portal/Uploads/102/worksheet.pdf".to_string(),
+            ..first.clone()
+        };
+        assert_ne!(
+            cache.attachment_path(&first),
+            cache.attachment_path(&second)
+        );
+        assert!(cache.attachment_path(&first).ends_with("worksheet.pdf"));
+
+        let path = cache.attachment_path(&first);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, b"first").unwrap();
+        assert_eq!(cache.attachment(&first), Some(path));
+        assert_eq!(cache.attachment(&second), None);
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("value.json");
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,361 @@
+use reqwest::{header, Client, RequestBuilder, StatusCode};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+use tl::{parse, ParserOptions};
+use tracing::{info, warn};
+
+use crate::{
+    error::{json_array, json_str, PortalError, Result},
+    listing::parse_listing,
+    login::Login,
+    Assignment, AssignmentDetails, AssignmentQuery, AssignmentType, Attachment, Cache, CleanString,
+    PortalConfig, Session, Subject, SyncDiff, SyncState,
+};
+
+const USER_AGENT: &str = "Mozilla/5.0 (Linux; Android 6.0; Nexus 5 Build/MRA58N) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/134.0.0.0 Mobile Safari/537.36";
+
+/// Authenticated client for the parent section of the portal.
+///
+/// Cloning is cheap: clones share the same connection pool and session, so a re-login through
+/// one clone is seen by all of them.
+#[derive(Debug, Clone)]
+pub struct PortalClient {
+    http: Client,
+    config: PortalConfig,
+    session: Arc<RwLock<Session>>,
+    session_file: Option<PathBuf>,
+}
+
+impl PortalClient {
+    pub fn new(config: PortalConfig, session: Session) -> Result<Self> {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            header::ACCEPT,
+            header::HeaderValue::from_static("application/json, text/javascript, */*; q=0.01"),
+        );
+        headers.insert(header::ORIGIN, config.origin().parse()?);
+        headers.insert(header::REFERER, config.url("Parent/Assignment").parse()?);
+        headers.insert(
+            "x-requested-with",
+            header::HeaderValue::from_static("XMLHttpRequest"),
+        );
+        let http = Client::builder()
+            .user_agent(USER_AGENT)
+            .default_headers(headers)
+            .build()?;
+        Ok(Self {
+            http,
+            config,
+            session: Arc::new(RwLock::new(session)),
+            session_file: None,
+        })
+    }
+
+    /// Saves the session to `path` whenever the client logs in again.
+    pub fn with_session_file(mut self, path: PathBuf) -> Self {
+        self.session_file = Some(path);
+        self
+    }
+
+    pub(crate) fn http(&self) -> &Client {
+        &self.http
+    }
+
+    pub fn config(&self) -> &PortalConfig {
+        &self.config
+    }
+
+    pub fn session(&self) -> Session {
+        self.session.read().unwrap().clone()
+    }
+
+    /// Replaces the session used by this client and all of its clones, saving it to the
+    /// session file if one is set.
+    pub fn set_session(&self, session: Session) {
+        if let Some(path) = &self.session_file {
+            if let Err(err) = session.save(path) {
+                warn!("Unable to save the session: {err:?}");
+            }
+        }
+        *self.session.write().unwrap() = session;
+    }
+
+    /// Checks whether the portal still accepts the session, without logging in again.
+    pub async fn validate_session(&self) -> Result<bool> {
+        Ok(self
+            .try_send(|| self.page_request("Parent/Assignment"))
+            .await?
+            .is_some())
+    }
+
+    /// Posts `form` to `path` and returns the decoded JSON body, logging in again once if the
+    /// session was rejected.
+    async fn post_form(&self, path: &str, form: &HashMap<&str, &str>) -> Result<serde_json::Value> {
+        let response = self
+            .send_with_relogin(|| {
+                self.http
+                    .post(self.config.url(path))
+                    .header(
+                        header::CONTENT_TYPE,
+                        "application/x-www-form-urlencoded; charset=UTF-8",
+                    )
+                    .form(form)
+            })
+            .await?;
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    /// Fetches `path` as a page, logging in again once if the session was rejected.
+    async fn get_page(&self, path: &str) -> Result<String> {
+        self.send_with_relogin(|| self.page_request(path)).await
+    }
+
+    fn page_request(&self, path: &str) -> RequestBuilder {
+        self.http
+            .get(self.config.url(path))
+            .header(header::ACCEPT, "text/html")
+    }
+
+    /// Sends the request made by `build` with the session cookies and returns the body.
+    ///
+    /// If the portal answers with its login page, the stored credentials are used to log in
+    /// again and a new request is sent once. [`PortalError::SessionExpired`] is returned when
+    /// that fails.
+    async fn send_with_relogin(&self, build: impl Fn() -> RequestBuilder) -> Result<String> {
+        if let Some(body) = self.try_send(&build).await? {
+            return Ok(body);
+        }
+        info!("Session expired, logging in again");
+        match Login::relogin(&self.config).await {
+            Ok(session) => self.set_session(session),
+            Err(err) => {
+                warn!("Unable to log in again: {err:?}");
+                return Err(PortalError::SessionExpired);
+            }
+        }
+        self.try_send(&build)
+            .await?
+            .ok_or(PortalError::SessionExpired)
+    }
+
+    /// Like [`Self::send_with_relogin`] without the retry. Returns `None` if the session was
+    /// rejected.
+    async fn try_send(&self, build: impl Fn() -> RequestBuilder) -> Result<Option<String>> {
+        let cookies = self.session().cookie_header(&self.config);
+        let response = build().header(header::COOKIE, cookies).send().await?;
+        let status = response.status();
+        let path = response.url().path().to_string();
+        let body = response.text().await?;
+        Ok(session_accepted(status, &path, &body)?.then_some(body))
+    }
+
+    /// Fetches the subjects of the logged-in student from the assignment page's subject dropdown.
+    pub async fn subjects(&self) -> Result<Vec<Subject>> {
+        let page = self.get_page("Parent/Assignment").await?;
+        parse_subjects(&page)
+    }
+
+    /// Looks up a subject by ID or name, as given on the command line.
+    pub async fn resolve_subject(&self, id_or_name: &str) -> Result<Subject> {
+        let subjects = self.subjects().await?;
+        Subject::find(&subjects, id_or_name)
+            .cloned()
+            .ok_or_else(|| PortalError::UnknownSubject(id_or_name.to_string()))
+    }
+
+    /// Fetches the assignments matching `query`, which can also be a bare [`AssignmentType`].
+    pub async fn list(&self, query: impl Into<AssignmentQuery>) -> Result<Vec<Assignment>> {
+        let query = query.into();
+        let type_ = query.type_;
+        let assign_type = type_.to_string();
+        let (from, to, subject) = query.form_values();
+        let mut form = HashMap::new();
+        form.insert("AssignType", assign_type.as_str());
+        form.insert("frmDate", from.as_str());
+        form.insert("toDate", to.as_str());
+        form.insert("Subject", subject.as_str());
+
+        let response = self
+            .post_form("Parent/AssignmentDetailsByAssignmentType", &form)
+            .await?;
+        let data = json_str(&response, "/Data/0")?;
+        let mut rows = parse_listing(data)?;
+        rows.iter_mut().for_each(|row| row.kind = type_);
+        Ok(rows)
+    }
+
+    /// Fetches the complete listing of `type_` and records it in `state`.
+    ///
+    /// With `check_details`, the details of every listed assignment are fetched as well and
+    /// stored in `cache`, and those revised since they were last stored are reported as changed.
+    pub async fn sync(
+        &self,
+        type_: AssignmentType,
+        state: &mut SyncState,
+        cache: &Cache,
+        check_details: bool,
+    ) -> Result<SyncDiff> {
+        let listing = self.list(type_).await?;
+        let mut diff = state.record(type_, &listing);
+        if check_details {
+            for assignment in &listing {
+                let details = self.details(assignment).await?;
+                if cache.store_details(&details)?
+                    && !diff
+                        .changed
+                        .iter()
+                        .any(|changed| changed.id == assignment.id)
+                {
+                    diff.changed.push(assignment.clone());
+                }
+            }
+        }
+        Ok(diff)
+    }
+
+    /// Fetches the details of an assignment, including its attachments.
+    pub async fn details(&self, assignment: &Assignment) -> Result<AssignmentDetails> {
+        let assign_type = assignment.kind.to_string();
+        let mut form = HashMap::new();
+        form.insert("frmDate", "");
+        form.insert("AssignType", assign_type.as_str());
+        form.insert("toDate", "");
+        form.insert("Subject", "0");
+        form.insert("AssigID", assignment.id.as_str());
+
+        let response = self.post_form("Parent/GetAssignemtDetails", &form).await?;
+        let attachments = self.parse_attachments(&response)?;
+        AssignmentDetails::parse(&response, assignment, attachments)
+    }
+
+    /// Reads the attachment list of a details response, skipping entries without a file name.
+    fn parse_attachments(&self, response: &serde_json::Value) -> Result<Vec<Attachment>> {
+        let attachments = json_array(response, "/Data/3")?;
+        let attachments = attachments
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, attachment)| {
+                let Some(filename) = attachment["Attachment"].as_str() else {
+                    warn!(
+                        "{}",
+                        PortalError::shape(format!("/Data/3/{idx}/Attachment"))
+                    );
+                    return None;
+                };
+                Some(Attachment {
+                    name: filename.to_string(),
+                    url: self.config.url(&format!("Assignment/{}", filename)),
+                })
+            });
+        Ok(attachments.collect::<Vec<_>>())
+    }
+}
+
+/// Whether the portal accepted the session for a response that ended up at `path`.
+///
+/// A rejected session shows up as a redirect to `/Logon`, a 401, or the login form served in
+/// place of the response. Any other failure is a [`PortalError::HttpStatus`].
+fn session_accepted(status: StatusCode, path: &str, body: &str) -> Result<bool> {
+    if path.contains("/Logon") || status == StatusCode::UNAUTHORIZED {
+        return Ok(false);
+    }
+    if !status.is_success() {
+        return Err(PortalError::HttpStatus(status));
+    }
+    Ok(!is_login_page(body))
+}
+
+/// The portal answers unauthenticated AJAX requests with its login form instead of JSON.
+fn is_login_page(body: &str) -> bool {
+    body.trim_start().starts_with('<')
+        && (body.contains("log[UserName]") || body.contains("Logon/Logon"))
+}
+
+/// Reads the options of the `Subject` dropdown, skipping the "select all" placeholder.
+fn parse_subjects(page: &str) -> Result<Vec<Subject>> {
+    let dom = parse(page, ParserOptions::default())?;
+    let parser = dom.parser();
+    let Some(select) = dom
+        .nodes()
+        .iter()
+        .filter_map(|node| node.as_tag())
+        .find(|tag| {
+            tag.name() == "select"
+                && [
+                    tag.attributes().id(),
+                    tag.attributes().get("name").flatten(),
+                ]
+                .into_iter()
+                .flatten()
+                .any(|value| value.as_utf8_str().eq_ignore_ascii_case("subject"))
+        })
+    else {
+        return Err(PortalError::shape("select#Subject"));
+    };
+
+    let subjects = select
+        .children()
+        .all(parser)
+        .iter()
+        .filter_map(|node| node.as_tag())
+        .filter(|tag| tag.name() == "option")
+        .filter_map(|option| {
+            let id = option.attributes().get("value").flatten()?.as_utf8_str();
+            let name = option.inner_text(parser).to_string().clean_string();
+            (!id.is_empty() && id != "0").then(|| Subject {
+                id: id.to_string(),
+                name,
+            })
+        })
+        .collect();
+    Ok(subjects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOGIN_PAGE: &str = r#"<!DOCTYPE html>
+<html><head><title>CampusCare</title></head><body>
+<form action="/Logon/Logon" method="post">
+  <input name="__RequestVerificationToken" type="hidden" value="token" />
+  <input id="log_UserName" name="log[UserName]" type="text" />
+  <input id="log_UserPassword" name="log[UserPassword]" type="password" />
+</form>
+</body></html>"#;
+
+    #[test]
+    fn test_server_error_is_not_an_expired_session() {
+        let page = "<html><body><h1>500 - Internal server error.</h1></body></html>";
+        let result = session_accepted(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "/Parent/Assignment",
+            page,
+        );
+        assert!(matches!(
+            result,
+            Err(PortalError::HttpStatus(StatusCode::INTERNAL_SERVER_ERROR))
+        ));
+        let result = session_accepted(StatusCode::BAD_GATEWAY, "/Parent/Assignment", "");
+        assert!(matches!(result, Err(PortalError::HttpStatus(_))));
+    }
+
+    #[test]
+    fn test_login_page_rejects_the_session() {
+        assert!(!session_accepted(StatusCode::OK, "/Parent/Assignment", LOGIN_PAGE).unwrap());
+        assert!(!session_accepted(StatusCode::OK, "/Logon/Logon", "").unwrap());
+        assert!(!session_accepted(StatusCode::UNAUTHORIZED, "/Parent/Assignment", "").unwrap());
+    }
+
+    #[test]
+    fn test_other_pages_are_accepted() {
+        let json = r#"{"Data": ["<table></table>"]}"#;
+        assert!(session_accepted(StatusCode::OK, "/Parent/Assignment", json).unwrap());
+        let page = "<html><body><select id=\"Subject\"></select></body></html>";
+        assert!(session_accepted(StatusCode::OK, "/Parent/Assignment", page).unwrap());
+        assert!(session_accepted(StatusCode::OK, "/Parent/Assignment", "").unwrap());
+    }
+}
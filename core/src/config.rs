@@ -0,0 +1,89 @@
+use reqwest::Url;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Connection settings for an Entab CampusCare portal.
+///
+/// Every school running CampusCare is served from its own host and is identified by a school
+/// code that the portal expects in the `SchoolCode` cookie.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PortalConfig {
+    /// Scheme and host of the portal, without a trailing slash.
+    #[serde(deserialize_with = "deserialize_base_url")]
+    pub base_url: String,
+    /// Value of the `SchoolCode` cookie.
+    pub school_code: String,
+    /// Value of `log[UserTypeID]` sent on login. `3` is the parent login.
+    pub user_type_id: String,
+}
+
+impl Default for PortalConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://www.lviscampuscare.org".to_string(),
+            school_code: "11674".to_string(),
+            user_type_id: "3".to_string(),
+        }
+    }
+}
+
+impl PortalConfig {
+    pub fn new(base_url: String, school_code: String, user_type_id: String) -> Self {
+        let mut config = Self {
+            base_url: String::new(),
+            school_code,
+            user_type_id,
+        };
+        config.set_base_url(&base_url);
+        config
+    }
+
+    /// Sets the base URL, dropping any trailing slash.
+    pub fn set_base_url(&mut self, base_url: &str) {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+    }
+
+    /// `scheme://host[:port]` of the base URL, as sent in the `Origin` header.
+    pub fn origin(&self) -> String {
+        match Url::parse(&self.base_url) {
+            Ok(url) => url.origin().ascii_serialization(),
+            Err(_) => self.base_url.clone(),
+        }
+    }
+
+    /// Joins `path` onto the portal's base URL.
+    pub fn url(&self, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.base_url.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        )
+    }
+}
+
+fn deserialize_base_url<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let base_url = String::deserialize(deserializer)?;
+    Ok(base_url.trim_end_matches('/').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_url_is_normalised() {
+        let config: PortalConfig =
+            serde_json::from_str(r#"{"base_url": "https://portal.example.org:8443/campus/"}"#)
+                .unwrap();
+        assert_eq!(config.base_url, "https://portal.example.org:8443/campus");
+        assert_eq!(config.origin(), "https://portal.example.org:8443");
+        assert_eq!(
+            config.url("/Parent/Assignment"),
+            "https://portal.example.org:8443/campus/Parent/Assignment"
+        );
+        assert_eq!(config.school_code, PortalConfig::default().school_code);
+    }
+}
@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use similar::{ChangeTag, TextDiff};
+use std::fmt;
+
+use crate::{
+    error::{json_at, json_str, Result},
+    Assignment, Attachment, CleanString, Link, RichText,
+};
+
+/// Everything the portal knows about a single assignment, from one details request.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssignmentDetails {
+    /// ID of the assignment these details belong to.
+    #[serde(default)]
+    pub id: String,
+    pub title: String,
+    pub issue_date: String,
+    pub due_date: Option<String>,
+    pub subject: String,
+    pub teacher: Option<String>,
+    /// Paragraphs of the body as plain text.
+    pub body: Vec<String>,
+    /// The body with its lists, tables, emphasis and links.
+    #[serde(default)]
+    pub rich_body: RichText,
+    /// The body as sent by the portal, for renderers that keep its formatting.
+    pub body_html: String,
+    pub attachments: Vec<Attachment>,
+}
+
+impl AssignmentDetails {
+    /// Reads a `Parent/GetAssignemtDetails` response. Fields the portal leaves out are taken
+    /// from the listing row.
+    pub(crate) fn parse(
+        response: &serde_json::Value,
+        assignment: &Assignment,
+        attachments: Vec<Attachment>,
+    ) -> Result<Self> {
+        let body_html = json_str(response, "/Data/0/Assignment")?.to_string();
+        let rich_body = RichText::from_html(&body_html)?;
+        let fields = json_at(response, "/Data/0")?;
+        let field = |keys: &[&str]| {
+            keys.iter()
+                .filter_map(|key| fields[*key].as_str())
+                .map(|value| value.trim().to_string().clean_string())
+                .find(|value| !value.is_empty())
+        };
+
+        Ok(Self {
+            id: assignment.id.clone(),
+            title: field(&["Title", "AssignmentTitle", "Topic"])
+                .unwrap_or_else(|| assignment.name.clone()),
+            issue_date: field(&["AssignmentDate", "IssueDate", "AssignDate"])
+                .unwrap_or_else(|| assignment.date.clone()),
+            due_date: field(&["SubmissionDate", "DueDate", "LastDate"]),
+            subject: field(&["SubjectName", "Subject"])
+                .unwrap_or_else(|| assignment.subject.clone()),
+            teacher: field(&["TeacherName", "EmpName", "Teacher"]),
+            body: rich_body
+                .plain_lines()
+                .into_iter()
+                .filter(|line| !line.is_empty())
+                .collect(),
+            rich_body,
+            body_html,
+            attachments,
+        })
+    }
+
+    /// Hash of what the teacher wrote: title, dates, body and attachments. Two fetches of an
+    /// unchanged assignment have the same hash.
+    pub fn content_hash(&self) -> String {
+        let content = serde_json::json!([
+            self.title,
+            self.issue_date,
+            self.due_date,
+            self.body_html,
+            self.attachments,
+        ]);
+        Sha256::digest(content.to_string().as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// The details as plain lines, header fields first, for comparing versions.
+    pub fn version_lines(&self) -> Vec<String> {
+        let mut lines = vec![self.title.clone(), format!("Issued: {}", self.issue_date)];
+        if let Some(due_date) = &self.due_date {
+            lines.push(format!("Due: {due_date}"));
+        }
+        lines.push(String::new());
+        lines.extend(self.rich_body.plain_lines());
+        if !self.attachments.is_empty() {
+            lines.push(String::new());
+            lines.extend(
+                self.attachments
+                    .iter()
+                    .map(|attachment| format!("Attachment: {}", attachment.name)),
+            );
+        }
+        lines
+    }
+}
+
+/// A line of a [`diff_lines`] result.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineChange {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+/// Line-level diff between two versions of a body, e.g. two [`RichText::plain_lines`].
+pub fn diff_lines(old: &[String], new: &[String]) -> Vec<LineChange> {
+    let old = old.iter().map(String::as_str).collect::<Vec<_>>();
+    let new = new.iter().map(String::as_str).collect::<Vec<_>>();
+    TextDiff::from_slices(&old, &new)
+        .iter_all_changes()
+        .map(|change| {
+            let line = change.value().to_string();
+            match change.tag() {
+                ChangeTag::Equal => LineChange::Same(line),
+                ChangeTag::Insert => LineChange::Added(line),
+                ChangeTag::Delete => LineChange::Removed(line),
+            }
+        })
+        .collect()
+}
+
+impl fmt::Display for AssignmentDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\r\n", self.title)?;
+        if !self.subject.is_empty() {
+            write!(f, "Subject: {}\r\n", self.subject)?;
+        }
+        if let Some(teacher) = &self.teacher {
+            write!(f, "Teacher: {teacher}\r\n")?;
+        }
+        write!(f, "Issued: {}\r\n", self.issue_date)?;
+        if let Some(due_date) = &self.due_date {
+            write!(f, "Due: {due_date}\r\n")?;
+        }
+        write!(f, "\r\n")?;
+        for line in self.rich_body.plain_lines() {
+            write!(f, "{line}\r\n")?;
+        }
+        if !self.attachments.is_empty() {
+            write!(f, "\r\n")?;
+        }
+        for attachment in &self.attachments {
+            let link = Link::new(attachment.name.clone(), attachment.url.clone());
+            write!(f, "{link}\r\n")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed(id: &str, name: &str, date: &str, subject: &str) -> Assignment {
+        Assignment {
+            id: id.to_string(),
+            name: name.to_string(),
+            date: date.to_string(),
+            subject: subject.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_details() {
+        let response =
+            serde_json::from_str(include_str!("../tests/fixtures/homework_details.json")).unwrap();
+        let assignment = listed("48213", "Ex 3.2", "14/04/2025", "Maths");
+        let attachments = vec![Attachment {
+            name: "Ex3.2_worksheet.pdf".to_string(),
+            url: "https://portal.example.org/Assignment/Ex3.2_worksheet.pdf".to_string(),
+        }];
+        let details =
+            AssignmentDetails::parse(&response, &assignment, attachments.clone()).unwrap();
+        assert_eq!(details.id, "48213");
+        assert_eq!(details.title, "Ex 3.2 – Q1 to Q10 in the notebook");
+        assert_eq!(details.issue_date, "14/04/2025");
+        assert_eq!(details.due_date.as_deref(), Some("17/04/2025"));
+        assert_eq!(details.subject, "Mathematics");
+        assert_eq!(details.teacher.as_deref(), Some("Mrs. R. Sharma"));
+        assert_eq!(
+            details.body,
+            [
+                "Solve Q1 to Q10 of Ex 3.2 in the maths notebook.",
+                "Show all the steps."
+            ]
+        );
+        assert!(details.body_html.starts_with("<p>Solve <b>"));
+        assert_eq!(details.attachments, attachments);
+    }
+
+    #[test]
+    fn test_parse_details_falls_back_to_the_listing() {
+        let response =
+            serde_json::from_str(include_str!("../tests/fixtures/circular_details.json")).unwrap();
+        let assignment = listed("9120", "Good Friday holiday", "11/04/2025", "");
+        let details = AssignmentDetails::parse(&response, &assignment, vec![]).unwrap();
+        assert_eq!(details.title, "Good Friday holiday");
+        assert_eq!(details.issue_date, "11/04/2025");
+        assert_eq!(details.due_date, None);
+        assert_eq!(details.subject, "");
+        assert_eq!(details.teacher, None);
+        assert_eq!(
+            details.body,
+            ["The school will remain closed on 18/04/2025 on account of Good Friday."]
+        );
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let lines = |lines: &[&str]| {
+            lines
+                .iter()
+                .map(|line| line.to_string())
+                .collect::<Vec<_>>()
+        };
+        let old = lines(&["Excursion to Science City", "Date: 18/04/2025", "Fee: ₹500"]);
+        let new = lines(&["Excursion to Science City", "Date: 25/04/2025", "Fee: ₹500"]);
+        assert_eq!(
+            diff_lines(&old, &new),
+            vec![
+                LineChange::Same("Excursion to Science City".to_string()),
+                LineChange::Removed("Date: 18/04/2025".to_string()),
+                LineChange::Added("Date: 25/04/2025".to_string()),
+                LineChange::Same("Fee: ₹500".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,689 @@
+use reqwest::{header, Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tempfile::{NamedTempFile, TempPath};
+use tokio::{
+    io::AsyncWriteExt,
+    sync::{mpsc, Semaphore},
+    task::AbortHandle,
+};
+
+use crate::{
+    error::{PortalError, Result},
+    Assignment, PortalClient,
+};
+
+/// Where downloaded attachments are saved.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadConfig {
+    /// Root download directory. Frontends pick a default when unset.
+    pub dir: Option<PathBuf>,
+    /// Save into a subfolder per student.
+    pub per_student: bool,
+    /// Save into a subfolder per assignment, named after its date and title.
+    pub per_assignment: bool,
+    /// How many attachments to fetch at once.
+    pub concurrency: usize,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            per_student: true,
+            per_assignment: true,
+            concurrency: DEFAULT_CONCURRENCY,
+        }
+    }
+}
+
+impl DownloadConfig {
+    /// The folder attachments of `assignment` go to, below `root`.
+    pub fn target_dir(
+        &self,
+        root: &Path,
+        student: &str,
+        assignment: Option<&Assignment>,
+    ) -> PathBuf {
+        let mut dir = root.to_path_buf();
+        if self.per_student {
+            dir.push(sanitize_filename(student));
+        }
+        if let Some(assignment) = assignment.filter(|_| self.per_assignment) {
+            let date = assignment
+                .issued
+                .map(|issued| issued.to_string())
+                .unwrap_or_else(|| assignment.date.clone());
+            dir.push(sanitize_filename(&format!("{date} {}", assignment.name)));
+        }
+        dir
+    }
+}
+
+/// Longest file name we create, in bytes. Most file systems allow 255.
+const MAX_NAME_LEN: usize = 150;
+
+/// Turns a server-provided name into a plain file name: no directories, no characters that
+/// are reserved on common file systems, and never `.`, `..` or empty.
+pub fn sanitize_filename(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    let name = name.trim_matches(|c: char| c == '.' || c.is_whitespace());
+    let name = truncate(name, MAX_NAME_LEN);
+    if name.is_empty() {
+        return "attachment".to_string();
+    }
+
+    // Windows reserves these names with any extension.
+    let stem = name
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || (stem.len() == 4
+            && (stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.ends_with(|c: char| c.is_ascii_digit()));
+    if reserved {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+/// Shortens `name` to at most `max` bytes, keeping its extension.
+fn truncate(name: &str, max: usize) -> String {
+    if name.len() <= max {
+        return name.to_string();
+    }
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if extension.len() < 16 => (stem, format!(".{extension}")),
+        _ => (name, String::new()),
+    };
+    let mut end = max.saturating_sub(extension.len());
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{extension}", &stem[..end])
+}
+
+/// The `index`th alternative for a taken name: `name (1).pdf`, `name (2).pdf` and so on.
+fn numbered(name: &str, index: usize) -> String {
+    if index == 0 {
+        return name.to_string();
+    }
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => format!("{stem} ({index}).{extension}"),
+        _ => format!("{name} ({index})"),
+    }
+}
+
+/// Moves a finished temporary file to the first free `name (n).ext` in `dir`, never
+/// overwriting an existing file.
+fn persist_unique(mut file: NamedTempFile, dir: &Path, name: &str) -> Result<PathBuf> {
+    for index in 0.. {
+        let path = dir.join(numbered(name, index));
+        match file.persist_noclobber(&path) {
+            Ok(_) => return Ok(path),
+            Err(err) if err.error.kind() == ErrorKind::AlreadyExists => file = err.file,
+            Err(err) => return Err(err.error.into()),
+        }
+    }
+    unreachable!("ran out of file names")
+}
+
+/// How many attachments a [`Downloader`] fetches at once unless told otherwise.
+pub const DEFAULT_CONCURRENCY: usize = 3;
+
+/// Minimum time between two [`DownloadEvent::Progress`] events of the same download.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Identifies one download started by a [`Downloader`].
+pub type DownloadId = usize;
+
+/// What a [`Downloader`] reports about its downloads.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DownloadEvent {
+    /// Bytes on disk so far, including any resumed part. `total` is unknown if the server
+    /// doesn't send a length.
+    Progress {
+        id: DownloadId,
+        received: u64,
+        total: Option<u64>,
+    },
+    Finished {
+        id: DownloadId,
+        path: PathBuf,
+    },
+    Failed {
+        id: DownloadId,
+        error: String,
+    },
+}
+
+/// A file to fetch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadJob {
+    pub url: String,
+    /// Folder to save into, created if missing.
+    pub dir: PathBuf,
+    /// Name to save as. It is sanitized, and numbered if taken unless `replace` is set.
+    pub name: String,
+    /// Overwrite a file of the same name instead of picking a free one.
+    pub replace: bool,
+}
+
+/// A download in progress.
+#[derive(Debug, Clone)]
+pub struct DownloadHandle {
+    pub id: DownloadId,
+    abort: AbortHandle,
+}
+
+impl DownloadHandle {
+    /// Stops the download. What was received so far is kept so it can be resumed.
+    pub fn cancel(&self) {
+        self.abort.abort();
+    }
+}
+
+/// Fetches attachments in the background, a few at a time.
+///
+/// Bodies are streamed to a `.part` file next to the destination, which is renamed into
+/// place once complete. If a `.part` file is left from an earlier attempt of the same URL,
+/// only the rest is requested with an HTTP `Range` header, guarded by `If-Range` so a file
+/// that changed on the server in between is fetched again from the start. Progress is sent over the channel returned by
+/// [`Downloader::new`]. Must be used from within a Tokio runtime.
+///
+/// Only one download per URL and folder runs at a time, since they would share a `.part`
+/// file. Starting another gives the handle of the one running.
+#[derive(Debug, Clone)]
+pub struct Downloader {
+    http: Client,
+    slots: Arc<Semaphore>,
+    next_id: Arc<AtomicUsize>,
+    events: mpsc::UnboundedSender<DownloadEvent>,
+    running: Running,
+}
+
+/// Downloads not finished yet, by folder and URL.
+type Running = Arc<Mutex<HashMap<(PathBuf, String), DownloadHandle>>>;
+
+/// Forgets a download once its task ends, completed or cancelled.
+struct RunningGuard {
+    running: Running,
+    key: (PathBuf, String),
+    id: DownloadId,
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        let mut running = self.running.lock().unwrap_or_else(|err| err.into_inner());
+        if running
+            .get(&self.key)
+            .is_some_and(|handle| handle.id == self.id)
+        {
+            running.remove(&self.key);
+        }
+    }
+}
+
+impl Downloader {
+    /// Creates a downloader running at most `concurrency` downloads at once.
+    pub fn new(
+        client: &PortalClient,
+        concurrency: usize,
+    ) -> (Self, mpsc::UnboundedReceiver<DownloadEvent>) {
+        let (events, receiver) = mpsc::unbounded_channel();
+        let downloader = Self {
+            http: client.http().clone(),
+            slots: Arc::new(Semaphore::new(concurrency.max(1))),
+            next_id: Arc::new(AtomicUsize::new(0)),
+            events,
+            running: Default::default(),
+        };
+        (downloader, receiver)
+    }
+
+    /// Queues `job`. It starts as soon as fewer than `concurrency` downloads are running.
+    /// If the same URL is already being fetched into the same folder, that download's handle
+    /// is returned instead.
+    pub fn start(&self, job: DownloadJob) -> DownloadHandle {
+        let key = (job.dir.clone(), job.url.clone());
+        let mut running = self.running.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(handle) = running.get(&key) {
+            return handle.clone();
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let http = self.http.clone();
+        let slots = self.slots.clone();
+        let events = self.events.clone();
+        let guard = RunningGuard {
+            running: self.running.clone(),
+            key: key.clone(),
+            id,
+        };
+        let task = tokio::spawn(async move {
+            let _guard = guard;
+            let Ok(_slot) = slots.acquire_owned().await else {
+                return;
+            };
+            let event = match fetch(&http, id, &job, &events).await {
+                Ok(path) => DownloadEvent::Finished { id, path },
+                Err(err) => DownloadEvent::Failed {
+                    id,
+                    error: err.to_string(),
+                },
+            };
+            let _ = events.send(event);
+        });
+        let handle = DownloadHandle {
+            id,
+            abort: task.abort_handle(),
+        };
+        running.insert(key, handle.clone());
+        handle
+    }
+}
+
+async fn fetch(
+    http: &Client,
+    id: DownloadId,
+    job: &DownloadJob,
+    events: &mpsc::UnboundedSender<DownloadEvent>,
+) -> Result<PathBuf> {
+    let name = sanitize_filename(&job.name);
+    tokio::fs::create_dir_all(&job.dir).await?;
+    let part = part_path(&job.dir, &job.url);
+    let validator_path = part.with_extension("validator");
+    let (mut offset, validator) = match tokio::fs::read_to_string(&validator_path).await {
+        Ok(validator) => match tokio::fs::metadata(&part).await {
+            Ok(metadata) => (metadata.len(), Some(validator)),
+            Err(err) if err.kind() == ErrorKind::NotFound => (0, None),
+            Err(err) => return Err(err.into()),
+        },
+        // Without a validator there's no telling whether the part is of the same file.
+        Err(err) if err.kind() == ErrorKind::NotFound => (0, None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut response = loop {
+        let mut request = http.get(&job.url);
+        if let Some(validator) = validator.as_ref().filter(|_| offset > 0) {
+            request = request
+                .header(header::RANGE, format!("bytes={offset}-"))
+                .header(header::IF_RANGE, validator.as_str());
+        }
+        let response = request.send().await?;
+        let content_range = response
+            .headers()
+            .get(header::CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok());
+        let resumable = match response.status() {
+            StatusCode::PARTIAL_CONTENT => {
+                content_range.and_then(content_range_start) == Some(offset)
+            }
+            // The part is already complete, if it is as long as the whole file.
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                content_range.and_then(content_range_total) == Some(offset)
+            }
+            _ => true,
+        };
+        if offset == 0 || resumable {
+            break response;
+        }
+        remove_if_exists(&part).await?;
+        offset = 0;
+    };
+    let (mut received, total) = match response.status() {
+        StatusCode::PARTIAL_CONTENT if offset > 0 => {
+            let total = response
+                .headers()
+                .get(header::CONTENT_RANGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(content_range_total)
+                .or_else(|| response.content_length().map(|length| offset + length));
+            (offset, total)
+        }
+        StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => (offset, Some(offset)),
+        status if status.is_success() => {
+            match response_validator(response.headers()) {
+                Some(validator) => tokio::fs::write(&validator_path, validator).await?,
+                None => remove_if_exists(&validator_path).await?,
+            }
+            (0, response.content_length())
+        }
+        status => return Err(PortalError::HttpStatus(status)),
+    };
+
+    if response.status() != StatusCode::RANGE_NOT_SATISFIABLE {
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(received > 0)
+            .truncate(received == 0)
+            .open(&part)
+            .await?;
+        let _ = events.send(DownloadEvent::Progress {
+            id,
+            received,
+            total,
+        });
+        let mut reported = Instant::now();
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk).await?;
+            received += chunk.len() as u64;
+            if reported.elapsed() >= PROGRESS_INTERVAL {
+                reported = Instant::now();
+                let _ = events.send(DownloadEvent::Progress {
+                    id,
+                    received,
+                    total,
+                });
+            }
+        }
+        file.sync_all().await?;
+    }
+    let _ = events.send(DownloadEvent::Progress {
+        id,
+        received,
+        total: total.or(Some(received)),
+    });
+    remove_if_exists(&validator_path).await?;
+
+    if job.replace {
+        let path = job.dir.join(&name);
+        tokio::fs::rename(&part, &path).await?;
+        return Ok(path);
+    }
+    let dir = job.dir.clone();
+    tokio::task::spawn_blocking(move || {
+        let file = fs::OpenOptions::new().read(true).open(&part)?;
+        persist_unique(
+            NamedTempFile::from_parts(file, TempPath::try_from_path(part)?),
+            &dir,
+            &name,
+        )
+    })
+    .await
+    .map_err(|err| PortalError::Io(err.into()))?
+}
+
+/// Where the download of `url` into `dir` is kept until complete. It is named after the URL
+/// rather than the file, so a part is only ever resumed from the same source.
+fn part_path(dir: &Path, url: &str) -> PathBuf {
+    dir.join(format!(".{}.part", url_hash(url)))
+}
+
+/// A short hash of `url`, to name files after where they came from.
+pub(crate) fn url_hash(url: &str) -> String {
+    hex::encode(&Sha256::digest(url.as_bytes())[..8])
+}
+
+/// What identifies this version of the file, to resume with `If-Range`: a strong `ETag`, or
+/// else `Last-Modified`.
+fn response_validator(headers: &header::HeaderMap) -> Option<&str> {
+    let etag = headers
+        .get(header::ETAG)
+        .and_then(|value| value.to_str().ok())
+        .filter(|etag| !etag.starts_with("W/"));
+    etag.or_else(|| {
+        headers
+            .get(header::LAST_MODIFIED)
+            .and_then(|value| value.to_str().ok())
+    })
+}
+
+async fn remove_if_exists(path: &Path) -> Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+/// The first byte from a `Content-Range: bytes 100-199/200` header.
+fn content_range_start(value: &str) -> Option<u64> {
+    let range = value.trim().strip_prefix("bytes ")?;
+    range.split_once('-')?.0.trim().parse().ok()
+}
+
+/// The complete length from a `Content-Range: bytes 100-199/200` header.
+fn content_range_total(value: &str) -> Option<u64> {
+    value.rsplit_once('/')?.1.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename("Worksheet 4.pdf"), "Worksheet 4.pdf");
+        assert_eq!(sanitize_filename("../../.bashrc"), "bashrc");
+        assert_eq!(sanitize_filename("..\\..\\evil.exe"), "evil.exe");
+        assert_eq!(sanitize_filename("a:b?c*.docx"), "a_b_c_.docx");
+        assert_eq!(sanitize_filename(".."), "attachment");
+        assert_eq!(sanitize_filename(""), "attachment");
+        assert_eq!(sanitize_filename("con.pdf"), "_con.pdf");
+        let long = format!("{}.pdf", "x".repeat(300));
+        assert_eq!(sanitize_filename(&long).len(), MAX_NAME_LEN);
+        assert!(sanitize_filename(&long).ends_with(".pdf"));
+    }
+
+    #[test]
+    fn test_persist_unique_resolves_collisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let save = |contents: &[u8]| {
+            let mut file = NamedTempFile::new_in(dir.path()).unwrap();
+            file.write_all(contents).unwrap();
+            persist_unique(file, dir.path(), "notes.pdf").unwrap()
+        };
+        let first = save(b"one");
+        let second = save(b"two");
+        let third = save(b"three");
+        assert_eq!(first, dir.path().join("notes.pdf"));
+        assert_eq!(second, dir.path().join("notes (1).pdf"));
+        assert_eq!(third, dir.path().join("notes (2).pdf"));
+        assert_eq!(fs::read(first).unwrap(), b"one");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    /// Serves one request per entry of `versions`, each a body with its `ETag`. A `Range:
+    /// bytes=n-` header is honoured if `If-Range` names the current version.
+    async fn serve(versions: Vec<(&'static [u8], &'static str)>) -> String {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for (body, etag) in versions {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![0; 4096];
+                let length = stream.read(&mut request).await.unwrap();
+                let request = String::from_utf8_lossy(&request[..length]).to_lowercase();
+                let header = |name: &str| {
+                    request
+                        .lines()
+                        .find_map(|line| line.strip_prefix(name).map(str::to_string))
+                };
+                let offset = header("range: bytes=")
+                    .filter(|_| header("if-range: ").as_deref() == Some(etag))
+                    .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok());
+                let (head, rest) = match offset {
+                    Some(offset) if offset >= body.len() => (
+                        format!(
+                            "416 Range Not Satisfiable\r\nContent-Length: 0\r\nContent-Range: bytes */{}",
+                            body.len()
+                        ),
+                        &body[..0],
+                    ),
+                    Some(offset) => (
+                        format!(
+                            "206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {offset}-{}/{}",
+                            body.len() - offset,
+                            body.len() - 1,
+                            body.len()
+                        ),
+                        &body[offset..],
+                    ),
+                    None => (format!("200 OK\r\nContent-Length: {}", body.len()), body),
+                };
+                let response = format!("HTTP/1.1 {head}\r\nETag: {etag}\r\n\r\n");
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.write_all(rest).await.unwrap();
+            }
+        });
+        format!("http://{address}/notes.pdf")
+    }
+
+    /// Downloads `url` as `notes.pdf` into `dir`, with the progress reported on the way.
+    async fn download(dir: &Path, url: String) -> (PathBuf, Vec<(u64, Option<u64>)>) {
+        let client = PortalClient::new(Default::default(), Default::default()).unwrap();
+        let (downloader, mut events) = Downloader::new(&client, 2);
+        let handle = downloader.start(DownloadJob {
+            url,
+            dir: dir.to_path_buf(),
+            name: "notes.pdf".to_string(),
+            replace: false,
+        });
+        let mut progress = vec![];
+        loop {
+            match events.recv().await.unwrap() {
+                DownloadEvent::Progress {
+                    received, total, ..
+                } => progress.push((received, total)),
+                DownloadEvent::Finished { id, path } => {
+                    assert_eq!(id, handle.id);
+                    return (path, progress);
+                }
+                DownloadEvent::Failed { error, .. } => panic!("{error}"),
+            }
+        }
+    }
+
+    /// Leaves a part of `url` as an earlier, interrupted attempt would.
+    fn leave_part(dir: &Path, url: &str, contents: &[u8], etag: &str) -> PathBuf {
+        let part = part_path(dir, url);
+        fs::write(&part, contents).unwrap();
+        fs::write(part.with_extension("validator"), etag).unwrap();
+        part
+    }
+
+    #[tokio::test]
+    async fn test_downloader_resumes_part_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("notes.pdf"), b"older").unwrap();
+        let url = serve(vec![(b"first half, second half", "\"v1\"")]).await;
+        let part = leave_part(dir.path(), &url, b"first half, ", "\"v1\"");
+
+        let (path, progress) = download(dir.path(), url).await;
+        assert_eq!(path, dir.path().join("notes (1).pdf"));
+        assert_eq!(fs::read(path).unwrap(), b"first half, second half");
+        assert_eq!(progress.first(), Some(&(12, Some(23))));
+        assert_eq!(progress.last(), Some(&(23, Some(23))));
+        assert!(!part.exists());
+        assert!(!part.with_extension("validator").exists());
+    }
+
+    #[tokio::test]
+    async fn test_downloader_restarts_when_the_file_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = serve(vec![(b"the revised notes", "\"v2\"")]).await;
+        leave_part(dir.path(), &url, b"first half, ", "\"v1\"");
+
+        let (path, progress) = download(dir.path(), url).await;
+        assert_eq!(fs::read(path).unwrap(), b"the revised notes");
+        assert_eq!(progress.first(), Some(&(0, Some(17))));
+    }
+
+    #[tokio::test]
+    async fn test_downloader_restarts_when_the_part_is_too_long() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = serve(vec![(b"short", "\"v1\""), (b"short", "\"v1\"")]).await;
+        leave_part(dir.path(), &url, b"longer than the file", "\"v1\"");
+
+        let (path, _) = download(dir.path(), url).await;
+        assert_eq!(fs::read(path).unwrap(), b"short");
+    }
+
+    #[tokio::test]
+    async fn test_downloader_accepts_a_complete_part() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = serve(vec![(b"complete", "\"v1\"")]).await;
+        leave_part(dir.path(), &url, b"complete", "\"v1\"");
+
+        let (path, progress) = download(dir.path(), url).await;
+        assert_eq!(fs::read(path).unwrap(), b"complete");
+        assert_eq!(progress, [(8, Some(8))]);
+    }
+
+    #[tokio::test]
+    async fn test_downloader_runs_a_job_once_at_a_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = PortalClient::new(Default::default(), Default::default()).unwrap();
+        let (downloader, mut events) = Downloader::new(&client, 2);
+        let job = DownloadJob {
+            url: serve(vec![(b"notes", "\"v1\"")]).await,
+            dir: dir.path().to_path_buf(),
+            name: "notes.pdf".to_string(),
+            replace: false,
+        };
+        let first = downloader.start(job.clone());
+        let second = downloader.start(job.clone());
+        assert_eq!(first.id, second.id);
+        loop {
+            match events.recv().await.unwrap() {
+                DownloadEvent::Progress { id, .. } => assert_eq!(id, first.id),
+                DownloadEvent::Finished { id, .. } => {
+                    assert_eq!(id, first.id);
+                    break;
+                }
+                DownloadEvent::Failed { error, .. } => panic!("{error}"),
+            }
+        }
+        assert_eq!(fs::read(dir.path().join("notes.pdf")).unwrap(), b"notes");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // Once finished, the same job can run again.
+        while !downloader.running.lock().unwrap().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        assert_ne!(downloader.start(job).id, first.id);
+    }
+
+    #[test]
+    fn test_part_path_depends_on_the_url() {
+        let dir = Path::new("downloads");
+        assert_eq!(part_path(dir, "https://a/1"), part_path(dir, "https://a/1"));
+        assert_ne!(part_path(dir, "https://a/1"), part_path(dir, "https://a/2"));
+    }
+
+    #[test]
+    fn test_content_range_total() {
+        assert_eq!(content_range_total("bytes 100-199/200"), Some(200));
+        assert_eq!(content_range_total("bytes 0-99/*"), None);
+        assert_eq!(content_range_total("bytes */300"), Some(300));
+        assert_eq!(content_range_start("bytes 100-199/200"), Some(100));
+        assert_eq!(content_range_start("bytes */300"), None);
+    }
+}
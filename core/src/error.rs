@@ -0,0 +1,75 @@
+use reqwest::StatusCode;
+
+pub type Result<T, E = PortalError> = std::result::Result<T, E>;
+
+/// Everything that can go wrong while talking to the portal.
+#[derive(Debug, thiserror::Error)]
+pub enum PortalError {
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("The portal answered with HTTP {0}")]
+    HttpStatus(StatusCode),
+    #[error("The portal session has expired, please log in again")]
+    SessionExpired,
+    #[error("Login failed: {0}")]
+    LoginFailed(String),
+    #[error("The login page has no `__RequestVerificationToken`")]
+    MissingVerificationToken,
+    #[error("The stored credentials are malformed")]
+    MalformedCredentials,
+    #[error("Missing environment variable {0}")]
+    MissingEnv(&'static str),
+    #[error("Unexpected response from the portal at `{path}`")]
+    UnexpectedShape { path: String },
+    #[error("Unknown subject `{0}`")]
+    UnknownSubject(String),
+    #[error("Row {row} of the assignment table is malformed")]
+    HtmlRowMalformed { row: usize },
+    #[error("Failed to parse HTML: {0}")]
+    Html(#[from] tl::ParseError),
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid header value: {0}")]
+    InvalidHeader(#[from] reqwest::header::InvalidHeaderValue),
+    #[error("Prompt failed: {0}")]
+    Prompt(#[from] inquire::InquireError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl PortalError {
+    /// Whether the portal could not be reached at all, as opposed to answering badly.
+    pub fn is_offline(&self) -> bool {
+        matches!(self, PortalError::Network(err) if err.is_connect() || err.is_timeout() || err.is_request())
+    }
+
+    pub(crate) fn shape(path: impl Into<String>) -> Self {
+        Self::UnexpectedShape { path: path.into() }
+    }
+}
+
+/// Looks up a JSON pointer such as `/Data/0`, failing with [`PortalError::UnexpectedShape`].
+pub(crate) fn json_at<'a>(
+    value: &'a serde_json::Value,
+    pointer: &str,
+) -> Result<&'a serde_json::Value> {
+    value
+        .pointer(pointer)
+        .filter(|value| !value.is_null())
+        .ok_or_else(|| PortalError::shape(pointer))
+}
+
+pub(crate) fn json_str<'a>(value: &'a serde_json::Value, pointer: &str) -> Result<&'a str> {
+    json_at(value, pointer)?
+        .as_str()
+        .ok_or_else(|| PortalError::shape(pointer))
+}
+
+pub(crate) fn json_array<'a>(
+    value: &'a serde_json::Value,
+    pointer: &str,
+) -> Result<&'a Vec<serde_json::Value>> {
+    json_at(value, pointer)?
+        .as_array()
+        .ok_or_else(|| PortalError::shape(pointer))
+}
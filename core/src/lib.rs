@@ -1,7 +1,21 @@
-#![allow(non_snake_case, clippy::collapsible_else_if)]
-
+#![allow(non_snake_case, clippy::collapsible_else_if, clippy::collapsible_match)]
+
+pub mod cache;
+pub mod client;
+pub mod config;
+pub mod details;
+pub mod download;
+pub mod error;
+pub mod listing;
 pub mod login;
-use anyhow::{Context, Ok, Result};
+pub mod query;
+pub mod rich_text;
+pub mod session;
+pub mod sync;
+pub use cache::{Cache, Cached, DetailsVersion};
+use chrono::NaiveDate;
+pub use client::PortalClient;
+pub use config::PortalConfig;
 use crossterm::{
     cursor::{Hide, MoveLeft, MoveRight, MoveTo, MoveToColumn, MoveUp, RestorePosition, Show},
     event::{self, KeyEvent, KeyModifiers},
@@ -15,131 +29,41 @@ use crossterm::{
     },
     ExecutableCommand, QueueableCommand,
 };
-use lazy_static::lazy_static;
-use reqwest::{header, Client};
+pub use details::{diff_lines, AssignmentDetails, LineChange};
+pub use download::{DownloadConfig, DownloadEvent, DownloadHandle, DownloadJob, Downloader};
+pub use error::{PortalError, Result};
+pub use listing::parse_listing;
+pub use query::{parse_portal_date, AssignmentQuery};
+pub use rich_text::{RichLine, RichSpan, RichText, SpanStyle};
 use serde::{Deserialize, Serialize};
+pub use session::Session;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::io::{stdout, Write};
-use std::{collections::HashMap, fmt};
-use std::{env, str::FromStr};
+use std::str::FromStr;
 use strum::EnumIter;
-use tl::{parse, ParserOptions};
-
-lazy_static! {
-    static ref SESSION_ID_STAT: String = env::var("ENTAB_SESSION_ID")
-        .context("Missing ENTAB_SESSION_ID")
-        .unwrap();
-    static ref REQUEST_VERIFICATION_TOKEN_STAT: String =
-        env::var("ENTAB_REQUEST_VERIFICATION_TOKEN")
-            .context("Missing ENTAB_REQUEST_VERIFICATION_TOKEN")
-            .unwrap();
-    static ref ASPXAUTH_STAT: String = env::var("ENTAB_ASPXAUTH")
-        .context("Missing ENTAB_ASPXAUTH")
-        .unwrap();
-}
+pub use sync::{SyncDiff, SyncState};
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct Assignment {
     pub id: String,
     pub name: String,
+    /// The issue date as printed by the portal.
     pub date: String,
+    /// [`Self::date`] parsed, `None` if the portal used an unknown layout.
+    #[serde(default)]
+    pub issued: Option<NaiveDate>,
     pub type_: String,
     pub s_no: String,
-}
-
-pub async fn get_circular() -> Result<Vec<Assignment>> {
-    let SESSION_ID: String = SESSION_ID_STAT.clone();
-    let REQUEST_VERIFICATION_TOKEN: String = REQUEST_VERIFICATION_TOKEN_STAT.clone();
-    let ASPXAUTH: String = ASPXAUTH_STAT.clone();
-    let client = Client::new();
-
-    let url = "https://www.lviscampuscare.org/Parent/AssignmentDetailsByAssignmentType";
-
-    let mut headers = header::HeaderMap::new();
-    headers.insert(
-        header::ACCEPT,
-        "application/json, text/javascript, */*; q=0.01"
-            .parse()
-            .unwrap(),
-    );
-    headers.insert(
-        header::CONTENT_TYPE,
-        "application/x-www-form-urlencoded; charset=UTF-8"
-            .parse()
-            .unwrap(),
-    );
-    headers.insert(
-        header::ORIGIN,
-        "https://www.lviscampuscare.org".parse().unwrap(),
-    );
-    headers.insert(
-        header::REFERER,
-        "https://www.lviscampuscare.org/Parent/Assignment"
-            .parse()
-            .unwrap(),
-    );
-    headers.insert(header::USER_AGENT, "Mozilla/5.0 (Linux; Android 6.0; Nexus 5 Build/MRA58N) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/134.0.0.0 Mobile Safari/537.36".parse().unwrap());
-    headers.insert("x-requested-with", "XMLHttpRequest".parse().unwrap());
-
-    let cookies = format!("ASP.NET_SessionId={}; chk=enable; __RequestVerificationToken={}; .ASPXAUTH={}; SchoolCode=11674", SESSION_ID, REQUEST_VERIFICATION_TOKEN, ASPXAUTH);
-    headers.insert(header::COOKIE, cookies.parse().unwrap());
-
-    let mut form = HashMap::new();
-    form.insert("AssignType", "C");
-    form.insert("frmDate", "");
-    form.insert("toDate", "");
-    form.insert("Subject", "");
-
-    let response = client
-        .post(url)
-        .headers(headers)
-        .form(&form)
-        .send()
-        .await?
-        .text()
-        .await
-        .context("Failed to get response")?;
-
-    let response: serde_json::Value =
-        serde_json::from_str(&response).context("Failed to parse response")?;
-    let data = response["Data"].as_array().unwrap()[0].as_str().unwrap();
-    let parsed_table = parse(data, ParserOptions::default())?;
-    let parser = parsed_table.parser();
-    let mut rows = vec![];
-    parsed_table.nodes().iter().for_each(|row| {
-        let tag = row.as_tag();
-
-        if let Some(tag) = tag {
-            if tag.name() != "tr" {
-                return;
-            }
-        }
-        let subnodes = row.children();
-        if let Some(subnodes) = subnodes {
-            let subnodes = subnodes.all(parser);
-            let mut row = vec![];
-            let mut id = String::new();
-            for subnode in subnodes {
-                let tag = subnode.as_tag();
-                if let Some(tag) = tag {
-                    let text = subnode.inner_text(parser).to_string();
-                    row.push(text.replace(['\r', '\n'], "").trim().to_string());
-                    if let Some(a_id) = tag.attributes().id() {
-                        id = a_id.to_owned().as_utf8_str().to_string();
-                    }
-                }
-            }
-            let row = Assignment {
-                s_no: row[0].clone(),
-                date: row[1].clone(),
-                type_: row[2].clone(),
-                name: row[3].clone().replace("&#39;", "'").clean_string(),
-                id,
-            };
-            rows.push(row);
-        }
-    });
-
-    Ok(rows)
+    /// The listing the assignment was fetched from.
+    #[serde(default)]
+    pub kind: AssignmentType,
+    /// Name of the subject, empty if the portal didn't list one.
+    #[serde(default)]
+    pub subject: String,
+    /// Columns of the listing that have no field of their own, by header.
+    #[serde(default)]
+    pub extra: BTreeMap<String, String>,
 }
 
 impl Assignment {
@@ -149,168 +73,12 @@ impl Assignment {
             self.s_no, self.id, self.date, self.type_, self.name
         )
     }
-    pub async fn get_details(&self, type_: AssignmentType) -> Result<String> {
-        let SESSION_ID: String = SESSION_ID_STAT.clone();
-        let REQUEST_VERIFICATION_TOKEN: String = REQUEST_VERIFICATION_TOKEN_STAT.clone();
-        let ASPXAUTH: String = ASPXAUTH_STAT.clone();
-
-        let client = Client::new();
-
-        let url = "https://www.lviscampuscare.org/Parent/GetAssignemtDetails";
-
-        let mut headers = header::HeaderMap::new();
-        headers.insert(
-            header::ACCEPT,
-            "application/json, text/javascript, */*; q=0.01"
-                .parse()
-                .unwrap(),
-        );
-        headers.insert(
-            header::CONTENT_TYPE,
-            "application/x-www-form-urlencoded; charset=UTF-8"
-                .parse()
-                .unwrap(),
-        );
-        headers.insert(
-            header::ORIGIN,
-            "https://www.lviscampuscare.org".parse().unwrap(),
-        );
-        headers.insert(
-            header::REFERER,
-            "https://www.lviscampuscare.org/Parent/Assignment"
-                .parse()
-                .unwrap(),
-        );
-        headers.insert(header::USER_AGENT, "Mozilla/5.0 (Linux; Android 6.0; Nexus 5 Build/MRA58N) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/134.0.0.0 Mobile Safari/537.36".parse().unwrap());
-        headers.insert("x-requested-with", "XMLHttpRequest".parse().unwrap());
-
-        let cookies = format!("ASP.NET_SessionId={}; chk=enable; __RequestVerificationToken={}; .ASPXAUTH={}; SchoolCode=11674", SESSION_ID, REQUEST_VERIFICATION_TOKEN, ASPXAUTH);
-        headers.insert(header::COOKIE, cookies.parse().unwrap());
-
-        let mut form = HashMap::new();
-        let type_ = type_.to_string();
-        form.insert("frmDate", "");
-        form.insert("AssignType", &type_);
-        form.insert("toDate", "");
-        form.insert("Subject", "0");
-        form.insert("AssigID", self.id.as_str());
-
-        let response = client
-            .post(url)
-            .headers(headers)
-            .form(&form)
-            .send()
-            .await?
-            .text()
-            .await
-            .context("Failed to get response")?;
-
-        let response: serde_json::Value =
-            serde_json::from_str(&response).context("Failed to parse response")?;
-        let data = response["Data"].as_array().unwrap()[0]["Assignment"]
-            .as_str()
-            .unwrap();
-        let parsed_table = parse(data, ParserOptions::default())?;
-        let mut out = String::new();
-        let parser = parsed_table.parser();
-        parsed_table.children().iter().for_each(|tag| {
-            let text = tag.get(parser).unwrap().inner_text(parser).to_string();
-            out.push_str(&text);
-            out.push_str("\r\n");
-        });
-
-        let attachments = response["Data"].as_array().unwrap()[3].as_array().unwrap();
-        let links = attachments.iter().map(|attachment| {
-            let filename = attachment["Attachment"].as_str().unwrap();
-            let url = format!("https://www.lviscampuscare.org/Assignment/{}", filename);
-            Link::new(filename.to_string(), url)
-        });
-        out.push_str("\r\n");
-        links.for_each(|link| {
-            out.push_str(link.url());
-            out.push('\t');
-        });
-        let out = out.clean_string();
-
-        Ok(out)
-    }
-
-    pub async fn get_attachments(&self, _assignment: &Assignment) -> Result<Vec<Attachment>> {
-        let SESSION_ID: String = SESSION_ID_STAT.clone();
-        let REQUEST_VERIFICATION_TOKEN: String = REQUEST_VERIFICATION_TOKEN_STAT.clone();
-        let ASPXAUTH: String = ASPXAUTH_STAT.clone();
-
-        let client = Client::new();
-
-        let url = "https://www.lviscampuscare.org/Parent/GetAssignemtDetails";
-
-        let mut headers = header::HeaderMap::new();
-        headers.insert(
-            header::ACCEPT,
-            "application/json, text/javascript, */*; q=0.01"
-                .parse()
-                .unwrap(),
-        );
-        headers.insert(
-            header::CONTENT_TYPE,
-            "application/x-www-form-urlencoded; charset=UTF-8"
-                .parse()
-                .unwrap(),
-        );
-        headers.insert(
-            header::ORIGIN,
-            "https://www.lviscampuscare.org".parse().unwrap(),
-        );
-        headers.insert(
-            header::REFERER,
-            "https://www.lviscampuscare.org/Parent/Assignment"
-                .parse()
-                .unwrap(),
-        );
-        headers.insert(header::USER_AGENT, "Mozilla/5.0 (Linux; Android 6.0; Nexus 5 Build/MRA58N) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/134.0.0.0 Mobile Safari/537.36".parse().unwrap());
-        headers.insert("x-requested-with", "XMLHttpRequest".parse().unwrap());
-
-        let cookies = format!("ASP.NET_SessionId={}; chk=enable; __RequestVerificationToken={}; .ASPXAUTH={}; SchoolCode=11674", SESSION_ID, REQUEST_VERIFICATION_TOKEN, ASPXAUTH);
-        headers.insert(header::COOKIE, cookies.parse().unwrap());
-
-        let mut form = HashMap::new();
-        let type_ = self.type_.to_string();
-        form.insert("frmDate", "");
-        form.insert("AssignType", &type_);
-        form.insert("toDate", "");
-        form.insert("Subject", "0");
-        form.insert("AssigID", self.id.as_str());
-
-        let response = client
-            .post(url)
-            .headers(headers)
-            .form(&form)
-            .send()
-            .await?
-            .text()
-            .await
-            .context("Failed to get response")?;
-
-        let response: serde_json::Value =
-            serde_json::from_str(&response).context("Failed to parse response")?;
-
-        let attachments = response["Data"].as_array().unwrap()[3].as_array().unwrap();
-        let attachments = attachments.iter().map(|attachment| {
-            let filename = attachment["Attachment"].as_str().unwrap();
-            let url = format!("https://www.lviscampuscare.org/Assignment/{}", filename);
-            Attachment {
-                name: filename.to_string(),
-                url,
-            }
-        });
-        Ok(attachments.collect::<Vec<_>>())
-    }
 }
 
 pub struct App {
+    client: PortalClient,
     assignments: Vec<Assignment>,
     selected_assignment: Option<Assignment>,
-    assignment_type: AssignmentType,
     window_start: usize,
     window_size: usize,
     mode: Modes,
@@ -318,11 +86,8 @@ pub struct App {
 }
 
 impl App {
-    pub async fn new(type_: AssignmentType) -> Self {
-        let assignments = match type_ {
-            AssignmentType::Circular => get_circular().await.unwrap(),
-            AssignmentType::Homework => homework::get_hw().await.unwrap(),
-        };
+    pub async fn new(client: PortalClient, query: AssignmentQuery) -> Result<Self> {
+        let assignments = client.list(query).await?;
         let assignments = assignments
             .into_iter()
             .map(|a| {
@@ -334,16 +99,16 @@ impl App {
             })
             .collect::<Vec<_>>();
         let selected_assignment = None;
-        let (_, window_size) = terminal::size().unwrap();
-        Self {
+        let (_, window_size) = terminal::size()?;
+        Ok(Self {
+            client,
             assignments,
             selected_assignment,
-            assignment_type: type_,
             window_start: 0,
-            window_size: window_size as usize - 2,
+            window_size: (window_size as usize).saturating_sub(2),
             mode: Modes::ViewingList,
             assignment_filter: String::new(),
-        }
+        })
     }
     pub fn change_mode(&mut self, mode: Modes) {
         self.mode = mode;
@@ -517,7 +282,7 @@ impl App {
             stdout().execute(PrintStyledContent("? Filter: ".with(Color::Green)))?;
             stdout().execute(Print(self.assignment_filter.clone()))?;
 
-            let event = event::read().unwrap();
+            let event = event::read()?;
             if let event::Event::Key(key) = event {
                 match self.mode {
                     Modes::ViewingList => self.handle_list_navigation(key).await?,
@@ -542,20 +307,19 @@ impl App {
                         ..
                     } => {
                         if let Some(selected_assignment) = self.get_selected_assignment() {
-                            let details = selected_assignment
-                                .get_details(self.assignment_type)
-                                .await
-                                .unwrap();
+                            let details = self.client.details(&selected_assignment).await?;
                             stdout().execute(LeaveAlternateScreen)?;
                             stdout().execute(Clear(ClearType::All))?;
                             stdout().execute(Print("\r\n"))?;
                             stdout().execute(Print(details))?;
                             stdout().execute(Print("\r\n"))?;
-                            stdout().execute(Print(SESSION_ID_STAT.to_string()))?;
+                            stdout().execute(Print(&self.client.session().session_id))?;
                             stdout().execute(Print("\r\n"))?;
-                            stdout().execute(Print(REQUEST_VERIFICATION_TOKEN_STAT.to_string()))?;
+                            stdout().execute(Print(
+                                &self.client.session().request_verification_token,
+                            ))?;
                             stdout().execute(Print("\r\n"))?;
-                            stdout().execute(Print(ASPXAUTH_STAT.to_string()))?;
+                            stdout().execute(Print(&self.client.session().aspxauth))?;
                             stdout().execute(Print("\r\n"))?;
 
                             stdout().execute(EnterAlternateScreen)?;
@@ -591,22 +355,17 @@ pub trait CleanString {
 
 impl CleanString for String {
     fn clean_string(self) -> String {
-        let replace_map = [
-            ("&nbsp;", " "),
-            ("&quot;", "\""),
-            ("&amp;", "&"),
-            ("&lt;", "<"),
-            ("&gt;", ">"),
-            ("&#39;", "'"),
-        ];
-        let mut out = self;
-        for (from, to) in replace_map.iter() {
-            out = out.replace(from, to);
-        }
-        out.trim().to_string()
+        decode_entities(&self).trim().to_string()
     }
 }
 
+/// Decodes HTML5 named entities and decimal/hex character references, without trimming.
+///
+/// Non-breaking spaces become plain spaces, since the portal uses `&nbsp;` for layout.
+pub fn decode_entities(text: &str) -> String {
+    html_escape::decode_html_entities(text).replace('\u{a0}', " ")
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Link {
     pub text: String,
@@ -635,7 +394,20 @@ impl fmt::Display for Link {
     }
 }
 
-#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, EnumIter)]
+#[derive(
+    Default,
+    Debug,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    EnumIter,
+)]
 pub enum AssignmentType {
     Circular,
     #[default]
@@ -662,113 +434,96 @@ impl FromStr for AssignmentType {
         }
     }
 }
-pub mod homework {
-    use super::*;
-    pub async fn get_hw() -> Result<Vec<Assignment>> {
-        let SESSION_ID: String = SESSION_ID_STAT.clone();
-        let REQUEST_VERIFICATION_TOKEN: String = REQUEST_VERIFICATION_TOKEN_STAT.clone();
-        let ASPXAUTH: String = ASPXAUTH_STAT.clone();
-        let client = Client::new();
-
-        let url = "https://www.lviscampuscare.org/Parent/AssignmentDetailsByAssignmentType";
-
-        let mut headers = header::HeaderMap::new();
-        headers.insert(
-            header::ACCEPT,
-            "application/json, text/javascript, */*; q=0.01"
-                .parse()
-                .unwrap(),
-        );
-        headers.insert(
-            header::CONTENT_TYPE,
-            "application/x-www-form-urlencoded; charset=UTF-8"
-                .parse()
-                .unwrap(),
-        );
-        headers.insert(
-            header::ORIGIN,
-            "https://www.lviscampuscare.org".parse().unwrap(),
-        );
-        headers.insert(
-            header::REFERER,
-            "https://www.lviscampuscare.org/Parent/Assignment"
-                .parse()
-                .unwrap(),
-        );
-        headers.insert(header::USER_AGENT, "Mozilla/5.0 (Linux; Android 6.0; Nexus 5 Build/MRA58N) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/134.0.0.0 Mobile Safari/537.36".parse().unwrap());
-        headers.insert("x-requested-with", "XMLHttpRequest".parse().unwrap());
-
-        let cookies = format!("ASP.NET_SessionId={}; chk=enable; __RequestVerificationToken={}; .ASPXAUTH={}; SchoolCode=11674", SESSION_ID, REQUEST_VERIFICATION_TOKEN, ASPXAUTH);
-        headers.insert(header::COOKIE, cookies.parse().unwrap());
-
-        let mut form = HashMap::new();
-        form.insert("AssignType", "H");
-        form.insert("frmDate", "");
-        form.insert("toDate", "");
-        form.insert("Subject", "");
-
-        let response = client
-            .post(url)
-            .headers(headers)
-            .form(&form)
-            .send()
-            .await?
-            .text()
-            .await
-            .context("Failed to get response")?;
-
-        let response: serde_json::Value =
-            serde_json::from_str(&response).context("Failed to parse response")?;
-        let data = response["Data"].as_array().unwrap()[0].as_str().unwrap();
-        let parsed_table = parse(data, ParserOptions::default())?;
-        let parser = parsed_table.parser();
-        let mut rows = vec![];
-        parsed_table.nodes().iter().for_each(|row| {
-            let tag = row.as_tag();
-
-            if let Some(tag) = tag {
-                if tag.name() != "tr" {
-                    return;
-                }
-            }
-            let subnodes = row.children();
-            if let Some(subnodes) = subnodes {
-                let subnodes = subnodes.all(parser);
-                let mut row = vec![];
-                let mut id = String::new();
-                for subnode in subnodes {
-                    let tag = subnode.as_tag();
-                    if let Some(tag) = tag {
-                        let text = subnode.inner_text(parser).to_string();
-                        row.push(text.replace(['\r', '\n'], "").trim().to_string());
-                        if let Some(a_id) = tag.attributes().id() {
-                            id = a_id.to_owned().as_utf8_str().to_string();
-                        }
-                    }
-                }
-                let row = Assignment {
-                    s_no: row[0].clone(),
-                    date: row[1].clone(),
-                    type_: row[2].clone(),
-                    name: row[3].clone().clean_string(),
-                    id,
-                };
-                rows.push(row);
-            }
-        });
-
-        Ok(rows)
-    }
-}
-
 #[derive(Clone, Debug)]
 pub enum Modes {
     ViewingList,
     Filtering,
 }
 
+/// A subject of the logged-in student, as listed in the portal's subject dropdown.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Subject {
+    /// Value sent in the `Subject` form field.
+    pub id: String,
+    pub name: String,
+}
+
+impl Subject {
+    /// Finds a subject by ID, or by name ignoring case.
+    pub fn find<'a>(subjects: &'a [Subject], id_or_name: &str) -> Option<&'a Subject> {
+        subjects.iter().find(|subject| {
+            subject.id == id_or_name || subject.name.eq_ignore_ascii_case(id_or_name)
+        })
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Attachment {
     pub name: String,
     pub url: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_string_named_entities() {
+        assert_eq!(
+            "Annual Sports Day &ndash; Schedule &amp; Guidelines&nbsp;"
+                .to_string()
+                .clean_string(),
+            "Annual Sports Day – Schedule & Guidelines"
+        );
+        assert_eq!(
+            "Parents&rsquo; Teacher Meeting (PTM) &ndash; Class VI&ndash;VIII"
+                .to_string()
+                .clean_string(),
+            "Parents’ Teacher Meeting (PTM) – Class VI–VIII"
+        );
+        assert_eq!(
+            "&ldquo;Save Water&rdquo; poster &hellip; A4 size"
+                .to_string()
+                .clean_string(),
+            "“Save Water” poster … A4 size"
+        );
+    }
+
+    #[test]
+    fn test_clean_string_numeric_entities() {
+        assert_eq!(
+            "Children&#8217;s Day celebration"
+                .to_string()
+                .clean_string(),
+            "Children’s Day celebration"
+        );
+        assert_eq!(
+            "Fee of &#x20B9;500 for the excursion"
+                .to_string()
+                .clean_string(),
+            "Fee of ₹500 for the excursion"
+        );
+        assert_eq!(
+            "Learn Ch-3 &#39;Nouns&#39; Q1&#x2013;Q10"
+                .to_string()
+                .clean_string(),
+            "Learn Ch-3 'Nouns' Q1–Q10"
+        );
+    }
+
+    #[test]
+    fn test_clean_string_decodes_once() {
+        assert_eq!(
+            "Write &amp;lt;title&amp;gt; on top"
+                .to_string()
+                .clean_string(),
+            "Write &lt;title&gt; on top"
+        );
+        assert_eq!(
+            "Fish &amp; Chips &unknown; 5 &lt; 6"
+                .to_string()
+                .clean_string(),
+            "Fish & Chips &unknown; 5 < 6"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use tl::{parse, HTMLTag, Parser, ParserOptions};
+use tracing::warn;
+
+use crate::{error::PortalError, error::Result, query::parse_portal_date, Assignment, CleanString};
+
+/// A column of the listing table that maps to a field of [`Assignment`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Column {
+    SNo,
+    Date,
+    Type,
+    Subject,
+    Title,
+}
+
+impl Column {
+    /// Recognises a header cell, ignoring case, spaces and punctuation.
+    fn from_header(header: &str) -> Option<Self> {
+        let key = header
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        match key.as_str() {
+            "sno" | "srno" | "slno" | "serialno" | "no" => Some(Column::SNo),
+            "date" | "assignmentdate" | "assigndate" | "issuedate" | "dateofissue" => {
+                Some(Column::Date)
+            }
+            "type" | "assignmenttype" | "assigntype" => Some(Column::Type),
+            "subject" | "subjectname" => Some(Column::Subject),
+            "title" | "assignment" | "assignmenttitle" | "topic" | "name" | "circular"
+            | "homework" | "description" => Some(Column::Title),
+            _ => None,
+        }
+    }
+}
+
+/// Layout used when the portal sends rows without a header.
+const DEFAULT_COLUMNS: [Column; 5] = [
+    Column::SNo,
+    Column::Date,
+    Column::Type,
+    Column::Title,
+    Column::Subject,
+];
+
+/// Parses an assignment or circular listing table.
+///
+/// Columns are matched by their header, so the portal can reorder them. Headers that don't
+/// match a field of [`Assignment`] end up in [`Assignment::extra`]. Without a header row the
+/// columns are read as S.No, Date, Type, Title, Subject. Rows without a title are skipped and
+/// logged.
+pub fn parse_listing(html: &str) -> Result<Vec<Assignment>> {
+    let dom = parse(html, ParserOptions::default())?;
+    let parser = dom.parser();
+    let rows = dom
+        .nodes()
+        .iter()
+        .filter_map(|node| node.as_tag())
+        .filter(|tag| tag.name() == "tr");
+
+    let mut headers: Option<Vec<(String, Option<Column>)>> = None;
+    let mut assignments = vec![];
+    let mut row_index = 0;
+    for row in rows {
+        let cells = cells(row, parser);
+        if cells.is_empty() {
+            continue;
+        }
+        if cells.iter().all(|cell| cell.header) {
+            headers = Some(
+                cells
+                    .into_iter()
+                    .map(|cell| {
+                        let column = Column::from_header(&cell.text);
+                        (cell.text, column)
+                    })
+                    .collect(),
+            );
+            continue;
+        }
+
+        row_index += 1;
+        let mut assignment = Assignment {
+            id: row_id(row, parser),
+            ..Default::default()
+        };
+        for (index, cell) in cells.into_iter().enumerate() {
+            let (name, column) = match &headers {
+                Some(headers) => match headers.get(index) {
+                    Some((name, column)) => (name.clone(), *column),
+                    None => (format!("Column {}", index + 1), None),
+                },
+                None => (
+                    format!("Column {}", index + 1),
+                    DEFAULT_COLUMNS.get(index).copied(),
+                ),
+            };
+            match column {
+                Some(Column::SNo) => assignment.s_no = cell.text,
+                Some(Column::Date) => {
+                    assignment.issued = parse_portal_date(&cell.text);
+                    assignment.date = cell.text;
+                }
+                Some(Column::Type) => assignment.type_ = cell.text,
+                Some(Column::Subject) => assignment.subject = cell.text,
+                Some(Column::Title) => assignment.name = cell.text,
+                None => {
+                    assignment.extra.insert(name, cell.text);
+                }
+            }
+        }
+        if assignment.name.is_empty() {
+            warn!(
+                "Skipping row: {}",
+                PortalError::HtmlRowMalformed { row: row_index }
+            );
+            continue;
+        }
+        assignments.push(assignment);
+    }
+    Ok(assignments)
+}
+
+struct Cell {
+    text: String,
+    header: bool,
+}
+
+/// The `td`/`th` cells of a row. Links inside them don't count as cells.
+fn cells(row: &HTMLTag, parser: &Parser) -> Vec<Cell> {
+    row.children()
+        .top()
+        .iter()
+        .filter_map(|handle| handle.get(parser)?.as_tag())
+        .filter(|cell| matches!(cell.name().as_bytes(), b"td" | b"th"))
+        .map(|cell| Cell {
+            text: cell
+                .inner_text(parser)
+                .replace(['\r', '\n'], " ")
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .clean_string(),
+            header: cell.name() == "th",
+        })
+        .collect()
+}
+
+/// The portal puts the assignment ID in the `id` of an element inside the row.
+fn row_id(row: &HTMLTag, parser: &Parser) -> String {
+    row.children()
+        .all(parser)
+        .iter()
+        .filter_map(|node| node.as_tag()?.attributes().id())
+        .next_back()
+        .map(|id| id.as_utf8_str().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_homework_with_header() {
+        let rows = parse_listing(include_str!("../tests/fixtures/homework_listing.html")).unwrap();
+        assert_eq!(rows.len(), 3);
+        let first = &rows[0];
+        assert_eq!(first.s_no, "1");
+        assert_eq!(first.id, "48213");
+        assert_eq!(first.date, "14/04/2025");
+        assert_eq!(first.issued, chrono::NaiveDate::from_ymd_opt(2025, 4, 14));
+        assert_eq!(first.type_, "Homework");
+        assert_eq!(first.subject, "Mathematics");
+        assert_eq!(first.name, "Ex 3.2 – Q1 to Q10 in the notebook");
+        assert_eq!(rows[2].subject, "Hindi");
+    }
+
+    #[test]
+    fn test_circular_without_header() {
+        let rows = parse_listing(include_str!("../tests/fixtures/circular_listing.html")).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].id, "9120");
+        assert_eq!(rows[0].type_, "Circular");
+        assert_eq!(
+            rows[0].name,
+            "Parents’ Teacher Meeting & Report Card Distribution"
+        );
+        assert_eq!(rows[1].date, "02/04/2025");
+        assert!(rows[1].extra.is_empty());
+    }
+
+    #[test]
+    fn test_reordered_columns_and_extras() {
+        let rows = parse_listing(include_str!("../tests/fixtures/reordered_listing.html")).unwrap();
+        assert_eq!(rows.len(), 2);
+        let first = &rows[0];
+        assert_eq!(first.name, "Science project – model of the water cycle");
+        assert_eq!(first.subject, "Science");
+        assert_eq!(first.date, "10/04/2025");
+        assert_eq!(first.s_no, "1");
+        assert_eq!(
+            first.extra.get("Teacher").map(String::as_str),
+            Some("Mrs. R. Sharma")
+        );
+        assert_eq!(
+            first.extra.get("Submission Date").map(String::as_str),
+            Some("21/04/2025")
+        );
+    }
+
+    #[test]
+    fn test_skips_rows_without_title() {
+        let html = "<table><tr><td>1</td><td>14/04/2025</td></tr>\
+                    <tr><td>2</td><td>15/04/2025</td><td>H</td><td>Read ch. 5</td></tr></table>";
+        let rows = parse_listing(html).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].name, "Read ch. 5");
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use home::home_dir;
 use inquire::Password;
 use inquire::Text;
@@ -9,11 +10,16 @@ use reqwest::{
     Client,
 };
 use sha1::{Digest, Sha1};
+use std::collections::HashMap;
 use std::io::Read;
 use std::io::Write;
-use std::{collections::HashMap, env};
 use tl::{parse, ParserOptions};
 
+use crate::{
+    error::{PortalError, Result},
+    PortalConfig, Session,
+};
+
 pub struct Login;
 
 lazy_static::lazy_static! {
@@ -21,28 +27,41 @@ lazy_static::lazy_static! {
 }
 
 impl Login {
-    pub fn store_credentials(username: &str, password: &str) {
-        std::fs::create_dir_all(DATA_DIR.clone()).unwrap();
-        let mut file = std::fs::File::create(format!("{}/credentials", DATA_DIR.clone())).unwrap();
-        file.write_all(format!("{}:{}", username, password).as_bytes())
-            .unwrap();
+    pub fn store_credentials(username: &str, password: &str) -> Result<()> {
+        std::fs::create_dir_all(DATA_DIR.clone())?;
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(format!("{}/credentials", DATA_DIR.clone()))?;
+        // `mode` only applies to newly created files.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        }
+        file.write_all(format!("{}:{}", username, password).as_bytes())?;
+        Ok(())
     }
-    pub fn fetch_credentials() -> Result<(String, String), anyhow::Error> {
+    pub fn fetch_credentials() -> Result<(String, String)> {
         let mut file = std::fs::File::open(format!("{}/credentials", DATA_DIR.clone()))?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
 
-        let mut split = contents.split(':');
-        let username = split.next().unwrap();
-        let password = split.next().unwrap();
+        let (username, password) = contents
+            .trim_end()
+            .rsplit_once(':')
+            .ok_or(PortalError::MalformedCredentials)?;
         Ok((username.to_string(), password.to_string()))
     }
     pub async fn login(
+        config: &PortalConfig,
         store_credentials: bool,
         fetch_credentials: bool,
-    ) -> Result<(), anyhow::Error> {
-        let res_token = Self::get_request_verification_token().await?;
-        env::set_var("ENTAB_REQUEST_VERIFICATION_TOKEN", &res_token);
+    ) -> Result<Session> {
         #[allow(unused_assignments)]
         let mut username = String::new();
         #[allow(unused_assignments)]
@@ -61,7 +80,7 @@ impl Login {
                     let hashed = hasher.finalize();
                     hash = hex::encode(hashed);
                     if store_credentials {
-                        Self::store_credentials(&username, &hash);
+                        Self::store_credentials(&username, &hash)?;
                     }
                 }
             }
@@ -73,10 +92,26 @@ impl Login {
             let hashed = hasher.finalize();
             hash = hex::encode(hashed);
             if store_credentials {
-                Self::store_credentials(&username, &hash);
+                Self::store_credentials(&username, &hash)?;
             }
         }
 
+        Self::login_with_credentials(config, &username, &hash).await
+    }
+
+    /// Logs in again with the stored credentials, without prompting.
+    pub async fn relogin(config: &PortalConfig) -> Result<Session> {
+        let (username, hash) = Self::fetch_credentials()?;
+        Self::login_with_credentials(config, &username, &hash).await
+    }
+
+    /// Logs in with a username and the hex-encoded SHA-1 of the password.
+    pub async fn login_with_credentials(
+        config: &PortalConfig,
+        username: &str,
+        hash: &str,
+    ) -> Result<Session> {
+        let res_token = Self::get_request_verification_token(config).await?;
         let client = Client::new();
 
         // Set up headers
@@ -90,18 +125,12 @@ impl Login {
             CONTENT_TYPE,
             HeaderValue::from_static("application/x-www-form-urlencoded; charset=UTF-8"),
         );
-        headers.insert(
-            ORIGIN,
-            HeaderValue::from_static("https://www.lviscampuscare.org"),
-        );
+        headers.insert(ORIGIN, HeaderValue::from_str(&config.origin())?);
         headers.insert(
             COOKIE,
             HeaderValue::from_str(format!("__RequestVerificationToken={}", res_token).as_str())?,
         );
-        headers.insert(
-            REFERER,
-            HeaderValue::from_static("https://www.lviscampuscare.org/Logon/Logon"),
-        );
+        headers.insert(REFERER, HeaderValue::from_str(&config.url("Logon/Logon"))?);
         headers.insert(USER_AGENT, HeaderValue::from_static("Mozilla/5.0 (Linux; Android 6.0; Nexus 5 Build/MRA58N) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/135.0.0.0 Mobile Safari/537.36"));
         headers.insert(
             "x-requested-with",
@@ -123,59 +152,56 @@ impl Login {
 
         // Raw body (URL-encoded)
         let mut form_data = HashMap::new();
-        form_data.insert("log[UserName]", username.as_str());
-        form_data.insert("log[UserPassword]", hash.as_str());
-        form_data.insert("log[UserTypeID]", "3");
+        form_data.insert("log[UserName]", username);
+        form_data.insert("log[UserPassword]", hash);
+        form_data.insert("log[UserTypeID]", config.user_type_id.as_str());
 
         // Send POST request
         let res = client
-            .post("https://www.lviscampuscare.org/Logon/Logon")
+            .post(config.url("Logon/Logon"))
             .headers(headers)
             .form(&form_data)
             .send()
             .await?;
 
-        let headers = res.headers();
-        let cookies = headers.get_all(SET_COOKIE).iter().collect::<Vec<_>>();
-        let cookies = cookies
-            .iter()
-            .map(|cookie| {
-                let cookie = cookie.to_str().unwrap();
-                let name = cookie.split("=").collect::<Vec<_>>()[0];
-                if name == "ASP.NET_SessionId" {
-                    let value = cookie.split("=").collect::<Vec<_>>()[1]
-                        .split(';')
-                        .next()
-                        .unwrap();
-                    ("ENTAB_SESSION_ID", value)
-                } else {
-                    let value = cookie.split("=").collect::<Vec<_>>()[1]
-                        .split(';')
-                        .next()
-                        .unwrap();
-                    ("ENTAB_ASPXAUTH", value)
+        let mut session = Session::new(String::new(), res_token, String::new());
+        session.username = Some(username.to_string());
+        for cookie in res.headers().get_all(SET_COOKIE) {
+            let Ok(cookie) = cookie.to_str() else {
+                continue;
+            };
+            let mut attributes = cookie.split(';');
+            let Some((name, value)) = attributes.next().and_then(|c| c.split_once('=')) else {
+                continue;
+            };
+            match name {
+                "ASP.NET_SessionId" => session.session_id = value.to_string(),
+                ".ASPXAUTH" => {
+                    session.aspxauth = value.to_string();
+                    session.expires_at = attributes.find_map(|attribute| {
+                        let (key, value) = attribute.trim().split_once('=')?;
+                        if !key.eq_ignore_ascii_case("expires") {
+                            return None;
+                        }
+                        // ASP.NET writes `Mon, 20-Oct-2025 10:00:00 GMT`.
+                        DateTime::parse_from_rfc2822(&value.replace('-', " "))
+                            .ok()
+                            .map(|date| date.with_timezone(&Utc))
+                    });
                 }
-            })
-            .collect::<Vec<_>>();
-        for (name, value) in &cookies {
-            env::set_var(name, value);
+                _ => {}
+            }
+        }
+        if session.aspxauth.is_empty() {
+            return Err(PortalError::LoginFailed(
+                "the portal did not return an authentication cookie".to_string(),
+            ));
         }
-        let mut export_command = String::from("export ");
-        cookies.iter().for_each(|(name, value)| {
-            env::set_var(name, value);
-            export_command.push_str(name);
-            export_command.push('=');
-            export_command.push_str(value);
-            export_command.push(' ');
-        });
-        export_command.push_str("ENTAB_REQUEST_VERIFICATION_TOKEN");
-        export_command.push('=');
-        export_command.push_str(&res_token);
 
-        Ok(())
+        Ok(session)
     }
 
-    pub async fn get_request_verification_token() -> Result<String, anyhow::Error> {
+    pub async fn get_request_verification_token(config: &PortalConfig) -> Result<String> {
         let client = reqwest::Client::builder().build()?;
 
         let mut headers = HeaderMap::new();
@@ -185,13 +211,10 @@ impl Login {
             HeaderValue::from_static("en-GB,en-US;q=0.9,en;q=0.8"),
         );
         headers.insert(USER_AGENT, HeaderValue::from_static("Mozilla/5.0 (Linux; Android 6.0; Nexus 5 Build/MRA58N) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/135.0.0.0 Mobile Safari/537.36"));
-        headers.insert(
-            REFERER,
-            HeaderValue::from_static("https://www.lviscampuscare.org/"),
-        );
+        headers.insert(REFERER, HeaderValue::from_str(&config.url(""))?);
 
         let res = client
-            .get("https://www.lviscampuscare.org/Logon/Logon")
+            .get(config.url("Logon/Logon"))
             .headers(headers.clone())
             .send()
             .await?;
@@ -199,7 +222,7 @@ impl Login {
         let body = res.text().await?;
 
         let parsed_table = parse(&body, ParserOptions::default())?;
-        let mut token = String::new();
+        let mut token = None;
         parsed_table.nodes().iter().for_each(|row| {
             let tag = row.as_tag();
             if let Some(tag) = tag {
@@ -207,19 +230,15 @@ impl Login {
                     return;
                 }
                 let attributes = tag.attributes();
-                if let Some(name) = attributes.get("name") {
-                    if name.unwrap().as_utf8_str() == "__RequestVerificationToken" {
-                        let value = attributes
-                            .get("value")
-                            .unwrap()
-                            .unwrap()
-                            .as_utf8_str()
-                            .to_string();
-                        token = value.to_string();
+                if let Some(Some(name)) = attributes.get("name") {
+                    if name.as_utf8_str() == "__RequestVerificationToken" {
+                        if let Some(Some(value)) = attributes.get("value") {
+                            token = Some(value.as_utf8_str().to_string());
+                        }
                     }
                 }
             }
         });
-        Ok(token)
+        token.ok_or(PortalError::MissingVerificationToken)
     }
 }
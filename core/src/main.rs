@@ -1,8 +1,11 @@
+use chrono::NaiveDate;
 use clap::Parser;
-use client_core::{login, AssignmentType};
+use client_core::{
+    login, session, AssignmentQuery, AssignmentType, PortalClient, PortalConfig, Session,
+};
 use crossterm::cursor::SavePosition;
 use crossterm::ExecutableCommand;
-use std::io::stdout;
+use std::{io::stdout, path::Path};
 use tracing_subscriber::field::MakeExt;
 
 #[derive(Parser, Debug)]
@@ -19,21 +22,103 @@ struct Args {
 
     #[arg(short, long, default_value_t = true)]
     fetch_credentials: bool,
+
+    /// Base URL of the CampusCare portal
+    #[arg(long)]
+    base_url: Option<String>,
+
+    /// School code sent in the `SchoolCode` cookie
+    #[arg(long)]
+    school_code: Option<String>,
+
+    /// User type ID sent on login
+    #[arg(long)]
+    user_type_id: Option<String>,
+
+    /// Only show assignments issued on or after this date (YYYY-MM-DD)
+    #[arg(long)]
+    from: Option<NaiveDate>,
+
+    /// Only show assignments issued on or before this date (YYYY-MM-DD)
+    #[arg(long)]
+    to: Option<NaiveDate>,
+
+    /// Only show assignments for this subject, by ID or name
+    #[arg(long)]
+    subject: Option<String>,
+
+    /// Print the subjects of the student and exit
+    #[arg(long)]
+    list_subjects: bool,
+}
+
+/// The saved session, else one from the environment, else a new login, the same as the TUI.
+async fn load_or_login(
+    config: &PortalConfig,
+    session_path: &Path,
+    login: bool,
+    store_credentials: bool,
+    fetch_credentials: bool,
+) -> anyhow::Result<Session> {
+    if !login {
+        match Session::load(session_path) {
+            Ok(Some(session)) if !session.is_expired() => return Ok(session),
+            Ok(_) => {}
+            Err(err) => eprintln!("Ignoring saved session: {err}"),
+        }
+        if let Ok(session) = Session::from_env() {
+            return Ok(session);
+        }
+    }
+    let session = login::Login::login(config, store_credentials, fetch_credentials).await?;
+    session.save(session_path)?;
+    Ok(session)
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let _cleanup = client_core::Cleanup;
     let args = Args::parse();
-    if args.login {
-        login::Login::login(args.store_credentials, args.fetch_credentials).await?;
+    let defaults = PortalConfig::default();
+    let config = PortalConfig::new(
+        args.base_url.unwrap_or(defaults.base_url),
+        args.school_code.unwrap_or(defaults.school_code),
+        args.user_type_id.unwrap_or(defaults.user_type_id),
+    );
+    let session_path = session::session_path();
+    let session = load_or_login(
+        &config,
+        &session_path,
+        args.login,
+        args.store_credentials,
+        args.fetch_credentials,
+    )
+    .await?;
+
+    let client = PortalClient::new(config, session)?.with_session_file(session_path);
+
+    if args.list_subjects {
+        for subject in client.subjects().await? {
+            println!("{}\t{}", subject.id, subject.name);
+        }
+        return Ok(());
     }
+    let subject = match args.subject {
+        Some(subject) => Some(client.resolve_subject(&subject).await?.id),
+        None => None,
+    };
+    let query = AssignmentQuery {
+        type_: args.type_,
+        from: args.from,
+        to: args.to,
+        subject,
+    };
 
     stdout().execute(SavePosition)?;
     tracing_subscriber::fmt()
         .map_fmt_fields(|f| f.debug_alt())
         .init();
-    let mut app = client_core::App::new(args.type_).await;
+    let mut app = client_core::App::new(client, query).await?;
     app.run().await?;
     Ok(())
 }
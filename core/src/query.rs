@@ -0,0 +1,88 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::AssignmentType;
+
+/// Date format the portal uses in its forms and tables.
+pub const PORTAL_DATE_FORMAT: &str = "%d/%m/%Y";
+
+/// Parses a date as the portal prints it, e.g. `14/04/2025`. A few other layouts seen in
+/// listings are accepted too.
+pub fn parse_portal_date(date: &str) -> Option<NaiveDate> {
+    [
+        PORTAL_DATE_FORMAT,
+        "%d-%m-%Y",
+        "%d-%b-%Y",
+        "%d %b %Y",
+        "%Y-%m-%d",
+    ]
+    .iter()
+    .find_map(|format| NaiveDate::parse_from_str(date.trim(), format).ok())
+}
+
+/// Filters for the assignment listing, sent as the `frmDate`, `toDate` and `Subject` form
+/// fields so the portal only returns matching rows.
+///
+/// ```
+/// # use client_core::{AssignmentQuery, AssignmentType};
+/// # use chrono::NaiveDate;
+/// let query = AssignmentQuery::new(AssignmentType::Circular)
+///     .from_date(NaiveDate::from_ymd_opt(2025, 4, 1).unwrap())
+///     .subject("12");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct AssignmentQuery {
+    pub type_: AssignmentType,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// Portal ID of the subject.
+    pub subject: Option<String>,
+}
+
+impl AssignmentQuery {
+    pub fn new(type_: AssignmentType) -> Self {
+        Self {
+            type_,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_date(mut self, date: NaiveDate) -> Self {
+        self.from = Some(date);
+        self
+    }
+
+    pub fn to_date(mut self, date: NaiveDate) -> Self {
+        self.to = Some(date);
+        self
+    }
+
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    /// Whether any filter besides the type is set.
+    pub fn is_filtered(&self) -> bool {
+        self.from.is_some() || self.to.is_some() || self.subject.is_some()
+    }
+
+    /// Values of the `frmDate`, `toDate` and `Subject` form fields. Empty means unfiltered.
+    pub(crate) fn form_values(&self) -> (String, String, String) {
+        let format = |date: Option<NaiveDate>| {
+            date.map(|date| date.format(PORTAL_DATE_FORMAT).to_string())
+                .unwrap_or_default()
+        };
+        (
+            format(self.from),
+            format(self.to),
+            self.subject.clone().unwrap_or_default(),
+        )
+    }
+}
+
+impl From<AssignmentType> for AssignmentQuery {
+    fn from(type_: AssignmentType) -> Self {
+        Self::new(type_)
+    }
+}
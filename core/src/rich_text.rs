@@ -0,0 +1,424 @@
+use serde::{Deserialize, Serialize};
+use tl::{parse, HTMLTag, Node, NodeHandle, Parser, ParserOptions};
+
+use crate::{decode_entities, error::Result};
+
+/// Styled text converted from the HTML the portal sends, independent of any UI toolkit.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RichText {
+    pub lines: Vec<RichLine>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RichLine {
+    pub spans: Vec<RichSpan>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RichSpan {
+    pub text: String,
+    pub style: SpanStyle,
+    /// Target of the hyperlink this span belongs to.
+    pub link: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SpanStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+impl RichLine {
+    /// The text of the line without styling.
+    pub fn plain(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    fn width(&self) -> usize {
+        self.spans
+            .iter()
+            .map(|span| span.text.chars().count())
+            .sum()
+    }
+}
+
+impl RichText {
+    /// Converts an HTML fragment, keeping paragraphs, lists, emphasis, tables and links.
+    pub fn from_html(html: &str) -> Result<Self> {
+        let dom = parse(html, ParserOptions::default())?;
+        let mut converter = Converter::new(dom.parser(), SpanStyle::default());
+        for handle in dom.children() {
+            converter.walk(*handle);
+        }
+        Ok(converter.finish())
+    }
+
+    /// The text of each line without styling.
+    pub fn plain_lines(&self) -> Vec<String> {
+        self.lines.iter().map(RichLine::plain).collect()
+    }
+}
+
+enum ListKind {
+    Bullet,
+    Numbered(usize),
+}
+
+/// Walks the DOM, collecting spans into lines.
+struct Converter<'p, 'a> {
+    parser: &'p Parser<'a>,
+    lines: Vec<RichLine>,
+    current: Vec<RichSpan>,
+    style: SpanStyle,
+    link: Option<String>,
+    lists: Vec<ListKind>,
+    /// Whitespace was seen since the last word and not yet written.
+    pending_space: bool,
+}
+
+impl<'p, 'a> Converter<'p, 'a> {
+    fn new(parser: &'p Parser<'a>, style: SpanStyle) -> Self {
+        Self {
+            parser,
+            lines: vec![],
+            current: vec![],
+            style,
+            link: None,
+            lists: vec![],
+            pending_space: false,
+        }
+    }
+
+    fn walk(&mut self, handle: NodeHandle) {
+        match handle.get(self.parser) {
+            Some(Node::Raw(text)) => self.text(&decode_entities(&text.as_utf8_str())),
+            Some(Node::Tag(tag)) => self.tag(tag),
+            Some(Node::Comment(_)) | None => {}
+        }
+    }
+
+    fn walk_children(&mut self, tag: &HTMLTag) {
+        for handle in tag.children().top().iter() {
+            self.walk(*handle);
+        }
+    }
+
+    /// Walks the children of `tag` with a changed style.
+    fn styled(&mut self, tag: &HTMLTag, change: impl FnOnce(&mut SpanStyle)) {
+        let saved = self.style;
+        change(&mut self.style);
+        self.walk_children(tag);
+        self.style = saved;
+    }
+
+    fn tag(&mut self, tag: &HTMLTag) {
+        let name = tag.name().as_utf8_str().to_ascii_lowercase();
+        match name.as_str() {
+            "script" | "style" | "head" | "title" => {}
+            "br" => self.break_line(true),
+            "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.break_line(false);
+                if name.starts_with('h') {
+                    self.styled(tag, |style| style.bold = true);
+                } else {
+                    self.walk_children(tag);
+                }
+                self.paragraph_break();
+            }
+            "div" | "blockquote" | "pre" | "center" | "section" => {
+                self.break_line(false);
+                self.walk_children(tag);
+                self.break_line(false);
+            }
+            "ul" | "ol" => {
+                self.break_line(false);
+                self.lists.push(match name.as_str() {
+                    "ol" => ListKind::Numbered(0),
+                    _ => ListKind::Bullet,
+                });
+                self.walk_children(tag);
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.paragraph_break();
+                }
+            }
+            "li" => {
+                self.break_line(false);
+                let depth = self.lists.len().saturating_sub(1);
+                let marker = match self.lists.last_mut() {
+                    Some(ListKind::Numbered(number)) => {
+                        *number += 1;
+                        format!("{number}. ")
+                    }
+                    Some(ListKind::Bullet) | None => "• ".to_string(),
+                };
+                self.push(
+                    format!("{}{marker}", "  ".repeat(depth)),
+                    SpanStyle::default(),
+                );
+                self.walk_children(tag);
+                self.break_line(false);
+            }
+            "table" => {
+                self.break_line(false);
+                self.table(tag);
+                self.paragraph_break();
+            }
+            "b" | "strong" | "th" => self.styled(tag, |style| style.bold = true),
+            "i" | "em" | "cite" => self.styled(tag, |style| style.italic = true),
+            "u" | "ins" => self.styled(tag, |style| style.underline = true),
+            "s" | "strike" | "del" => self.styled(tag, |style| style.strikethrough = true),
+            "a" => self.anchor(tag),
+            _ => self.walk_children(tag),
+        }
+    }
+
+    fn anchor(&mut self, tag: &HTMLTag) {
+        let href = tag
+            .attributes()
+            .get("href")
+            .flatten()
+            .map(|href| decode_entities(&href.as_utf8_str()));
+        let saved = self.link.clone();
+        self.link = href.clone();
+        let start = self.current.len();
+        self.walk_children(tag);
+        self.link = saved;
+
+        // Keep the target readable where the text doesn't already show it.
+        if let Some(href) = href.filter(|href| !href.is_empty()) {
+            let text = self.current[start.min(self.current.len())..]
+                .iter()
+                .map(|span| span.text.as_str())
+                .collect::<String>();
+            if text.trim() != href {
+                self.current.push(RichSpan {
+                    text: format!(" <{href}>"),
+                    style: self.style,
+                    link: Some(href),
+                });
+            }
+        }
+    }
+
+    /// Renders a table with its columns padded to the same width.
+    fn table(&mut self, table: &HTMLTag) {
+        let mut rows = vec![];
+        collect_rows(table, self.parser, &mut rows);
+        let mut header = false;
+        let cells = rows
+            .iter()
+            .map(|row| {
+                row.children()
+                    .top()
+                    .iter()
+                    .filter_map(|handle| handle.get(self.parser)?.as_tag())
+                    .filter(|cell| matches!(cell.name().as_bytes(), b"td" | b"th"))
+                    .map(|cell| {
+                        header |= cell.name() == "th";
+                        self.cell(cell)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|row| !row.is_empty())
+            .collect::<Vec<_>>();
+
+        let columns = cells.iter().map(Vec::len).max().unwrap_or(0);
+        let widths = (0..columns)
+            .map(|column| {
+                cells
+                    .iter()
+                    .filter_map(|row| row.get(column))
+                    .map(RichLine::width)
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+
+        for (index, row) in cells.into_iter().enumerate() {
+            let last = row.len().saturating_sub(1);
+            for (column, cell) in row.into_iter().enumerate() {
+                let padding = widths[column] - cell.width();
+                self.current.extend(cell.spans);
+                if column != last {
+                    self.push(format!("{} │ ", " ".repeat(padding)), SpanStyle::default());
+                }
+            }
+            self.break_line(false);
+            if index == 0 && header {
+                let rule = widths
+                    .iter()
+                    .map(|width| "─".repeat(*width))
+                    .collect::<Vec<_>>()
+                    .join("─┼─");
+                self.push(rule, SpanStyle::default());
+                self.break_line(false);
+            }
+        }
+    }
+
+    /// Converts a table cell to a single line.
+    fn cell(&self, cell: &HTMLTag) -> RichLine {
+        let mut converter = Converter::new(self.parser, self.style);
+        converter.styled(cell, |style| style.bold |= cell.name() == "th");
+        let mut spans = vec![];
+        for line in converter.finish().lines {
+            if !spans.is_empty() && !line.spans.is_empty() {
+                spans.push(RichSpan {
+                    text: " ".to_string(),
+                    ..Default::default()
+                });
+            }
+            spans.extend(line.spans);
+        }
+        RichLine { spans }
+    }
+
+    /// Adds text, collapsing runs of whitespace like a browser does.
+    fn text(&mut self, text: &str) {
+        let mut out = String::new();
+        for word in text.split_inclusive(char::is_whitespace) {
+            let trimmed = word.trim_end();
+            if !trimmed.is_empty() {
+                if self.pending_space && (!self.current.is_empty() || !out.is_empty()) {
+                    // Spaces go to plain text where possible, so styled runs don't start or end
+                    // with one.
+                    match self.current.last_mut() {
+                        Some(last)
+                            if out.is_empty()
+                                && last.style == SpanStyle::default()
+                                && last.link.is_none() =>
+                        {
+                            last.text.push(' ')
+                        }
+                        _ => out.push(' '),
+                    }
+                }
+                out.push_str(trimmed);
+                self.pending_space = false;
+            }
+            if trimmed.len() != word.len() {
+                self.pending_space = true;
+            }
+        }
+        if !out.is_empty() {
+            self.push(out, self.style);
+        }
+    }
+
+    fn push(&mut self, text: String, style: SpanStyle) {
+        match self.current.last_mut() {
+            Some(last) if last.style == style && last.link == self.link => {
+                last.text.push_str(&text)
+            }
+            _ => self.current.push(RichSpan {
+                text,
+                style,
+                link: self.link.clone(),
+            }),
+        }
+    }
+
+    /// Ends the current line. Empty lines are only kept for `<br>`.
+    fn break_line(&mut self, force: bool) {
+        if force || !self.current.is_empty() {
+            let spans = std::mem::take(&mut self.current);
+            self.lines.push(RichLine { spans });
+        }
+        self.pending_space = false;
+    }
+
+    /// Ends the current line and leaves one blank line before the next block.
+    fn paragraph_break(&mut self) {
+        self.break_line(false);
+        if self.lines.last().is_some_and(|line| !line.spans.is_empty()) {
+            self.lines.push(RichLine::default());
+        }
+    }
+
+    fn finish(mut self) -> RichText {
+        self.break_line(false);
+        while self.lines.last().is_some_and(|line| line.spans.is_empty()) {
+            self.lines.pop();
+        }
+        let first = self
+            .lines
+            .iter()
+            .position(|line| !line.spans.is_empty())
+            .unwrap_or(self.lines.len());
+        self.lines.drain(..first);
+        RichText { lines: self.lines }
+    }
+}
+
+/// Finds the rows of a table, looking through `thead`/`tbody` but not into nested tables.
+fn collect_rows<'p, 'a>(
+    tag: &'p HTMLTag<'a>,
+    parser: &'p Parser<'a>,
+    rows: &mut Vec<&'p HTMLTag<'a>>,
+) {
+    for handle in tag.children().top().iter() {
+        let Some(child) = handle.get(parser).and_then(Node::as_tag) else {
+            continue;
+        };
+        match child.name().as_bytes() {
+            b"tr" => rows.push(child),
+            b"table" => {}
+            _ => collect_rows(child, parser, rows),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(html: &str) -> Vec<String> {
+        RichText::from_html(html).unwrap().plain_lines()
+    }
+
+    #[test]
+    fn test_paragraphs_and_breaks() {
+        assert_eq!(
+            plain("<p>Read   chapter\n 4.</p><p>Answer<br>all questions</p>"),
+            vec!["Read chapter 4.", "", "Answer", "all questions"]
+        );
+    }
+
+    #[test]
+    fn test_lists() {
+        assert_eq!(
+            plain("<ol><li>Q1</li><li>Q2<ul><li>part a</li></ul></li></ol>"),
+            vec!["1. Q1", "2. Q2", "  • part a"]
+        );
+    }
+
+    #[test]
+    fn test_emphasis_and_links() {
+        let text =
+            RichText::from_html(r#"Do <b>all</b> of <a href="https://example.com/w.pdf">this</a>"#)
+                .unwrap();
+        let spans = &text.lines[0].spans;
+        assert_eq!(spans[1].text, "all");
+        assert!(spans[1].style.bold);
+        assert_eq!(spans[3].text, "this");
+        assert_eq!(spans[3].link.as_deref(), Some("https://example.com/w.pdf"));
+        assert_eq!(
+            text.lines[0].plain(),
+            "Do all of this <https://example.com/w.pdf>"
+        );
+    }
+
+    #[test]
+    fn test_table() {
+        assert_eq!(
+            plain(
+                "<table><tr><th>Subject</th><th>Pages</th></tr>\
+                 <tr><td>Maths</td><td>12-14</td></tr></table>"
+            ),
+            vec!["Subject │ Pages", "────────┼──────", "Maths   │ 12-14"]
+        );
+    }
+}
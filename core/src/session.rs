@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{PortalError, Result},
+    PortalConfig,
+};
+
+/// Folder the frontends keep their data in: `$ENTAB_TUI_DATA` if set, else the local data
+/// folder of the platform.
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("ENTAB_TUI_DATA") {
+        PathBuf::from(dir)
+    } else if let Some(dirs) = ProjectDirs::from("com", "jayanaxhf", "entab_tui") {
+        dirs.data_local_dir().to_path_buf()
+    } else {
+        PathBuf::from(".").join(".data")
+    }
+}
+
+/// Where the session is saved between runs.
+pub fn session_path() -> PathBuf {
+    data_dir().join("session.json")
+}
+
+/// Cookies identifying a logged-in portal session.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Session {
+    /// Value of the `ASP.NET_SessionId` cookie.
+    pub session_id: String,
+    /// Value of the `__RequestVerificationToken` cookie.
+    pub request_verification_token: String,
+    /// Value of the `.ASPXAUTH` cookie.
+    pub aspxauth: String,
+    /// When the session was obtained.
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    /// Expiry of the `.ASPXAUTH` cookie, if the portal sent one.
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// The login the session belongs to, which identifies the student.
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+impl Session {
+    pub fn new(session_id: String, request_verification_token: String, aspxauth: String) -> Self {
+        Self {
+            session_id,
+            request_verification_token,
+            aspxauth,
+            created_at: Some(Utc::now()),
+            expires_at: None,
+            username: None,
+        }
+    }
+
+    /// Whether the authentication cookie is past its expiry. Sessions without a known expiry
+    /// are assumed to be live.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+
+    /// Loads a session saved with [`Session::save`]. Returns `None` if there is no saved session.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let session = serde_json::from_str(&contents)?;
+        Ok(Some(session))
+    }
+
+    /// Saves the session to `path`, readable only by the current user.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(path)?;
+        // `mode` only applies to newly created files.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        }
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Deletes a saved session. Does nothing if there is none.
+    pub fn clear(path: &Path) -> Result<()> {
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads a session exported by hand through `ENTAB_SESSION_ID`,
+    /// `ENTAB_REQUEST_VERIFICATION_TOKEN` and `ENTAB_ASPXAUTH`.
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            session_id: env::var("ENTAB_SESSION_ID")
+                .map_err(|_| PortalError::MissingEnv("ENTAB_SESSION_ID"))?,
+            request_verification_token: env::var("ENTAB_REQUEST_VERIFICATION_TOKEN")
+                .map_err(|_| PortalError::MissingEnv("ENTAB_REQUEST_VERIFICATION_TOKEN"))?,
+            aspxauth: env::var("ENTAB_ASPXAUTH")
+                .map_err(|_| PortalError::MissingEnv("ENTAB_ASPXAUTH"))?,
+            ..Default::default()
+        })
+    }
+
+    /// Name under which per-student state is kept, `default` if the login is unknown.
+    pub fn student(&self) -> &str {
+        self.username.as_deref().unwrap_or("default")
+    }
+
+    /// Builds the `Cookie` header value the portal expects on authenticated requests.
+    pub fn cookie_header(&self, config: &PortalConfig) -> String {
+        format!(
+            "ASP.NET_SessionId={}; chk=enable; __RequestVerificationToken={}; .ASPXAUTH={}; SchoolCode={}",
+            self.session_id, self.request_verification_token, self.aspxauth, config.school_code
+        )
+    }
+}
@@ -0,0 +1,151 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    io::ErrorKind,
+    path::Path,
+};
+
+use crate::{cache::write_atomic, error::Result, Assignment, AssignmentType};
+
+/// What changed in a listing since it was last recorded.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncDiff {
+    pub new: Vec<Assignment>,
+    pub removed: Vec<Assignment>,
+    /// Assignments whose row or details differ from the last recorded version.
+    pub changed: Vec<Assignment>,
+}
+
+impl SyncDiff {
+    pub fn is_empty(&self) -> bool {
+        self.new.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// The assignments seen so far for one student, to tell what's new after each fetch.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncState {
+    listings: BTreeMap<AssignmentType, SeenListing>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct SeenListing {
+    synced_at: Option<DateTime<Utc>>,
+    seen: BTreeMap<String, Assignment>,
+}
+
+impl SyncState {
+    /// Loads the state saved with [`SyncState::save`], or an empty one.
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write_atomic(path, serde_json::to_string(self)?.as_bytes())
+    }
+
+    /// When a listing of `type_` was last recorded.
+    pub fn synced_at(&self, type_: AssignmentType) -> Option<DateTime<Utc>> {
+        self.listings.get(&type_)?.synced_at
+    }
+
+    /// Compares a complete listing with the last recorded one.
+    pub fn diff(&self, type_: AssignmentType, listing: &[Assignment]) -> SyncDiff {
+        let empty = SeenListing::default();
+        let seen = &self.listings.get(&type_).unwrap_or(&empty).seen;
+        let ids = listing
+            .iter()
+            .map(|assignment| assignment.id.as_str())
+            .collect::<HashSet<_>>();
+
+        let mut diff = SyncDiff::default();
+        for assignment in listing {
+            match seen.get(&assignment.id) {
+                None => diff.new.push(assignment.clone()),
+                Some(previous) if !same_row(previous, assignment) => {
+                    diff.changed.push(assignment.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = seen
+            .values()
+            .filter(|assignment| !ids.contains(assignment.id.as_str()))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    /// Records a complete listing of `type_`, returning how it differs from the last one.
+    pub fn record(&mut self, type_: AssignmentType, listing: &[Assignment]) -> SyncDiff {
+        let diff = self.diff(type_, listing);
+        self.listings.insert(
+            type_,
+            SeenListing {
+                synced_at: Some(Utc::now()),
+                seen: listing
+                    .iter()
+                    .map(|assignment| (assignment.id.clone(), assignment.clone()))
+                    .collect(),
+            },
+        );
+        diff
+    }
+}
+
+/// Whether two versions of a row show the same assignment. The serial number is ignored since
+/// it shifts whenever something new comes in.
+fn same_row(a: &Assignment, b: &Assignment) -> bool {
+    a.name == b.name
+        && a.date == b.date
+        && a.type_ == b.type_
+        && a.subject == b.subject
+        && a.extra == b.extra
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assignment(id: &str, name: &str, s_no: &str) -> Assignment {
+        Assignment {
+            id: id.to_string(),
+            name: name.to_string(),
+            s_no: s_no.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_record_reports_new_removed_and_changed() {
+        let mut state = SyncState::default();
+        let first = [
+            assignment("1", "Read ch. 4", "1"),
+            assignment("2", "Map work", "2"),
+        ];
+        assert_eq!(state.record(AssignmentType::Homework, &first).new.len(), 2);
+
+        let second = [
+            assignment("3", "Poem", "1"),
+            assignment("1", "Read ch. 4 and 5", "2"),
+        ];
+        let diff = state.record(AssignmentType::Homework, &second);
+        assert_eq!(diff.new, vec![second[0].clone()]);
+        assert_eq!(diff.removed, vec![first[1].clone()]);
+        assert_eq!(diff.changed, vec![second[1].clone()]);
+
+        // Only the serial number moved.
+        let third = [
+            assignment("3", "Poem", "2"),
+            assignment("1", "Read ch. 4 and 5", "1"),
+        ];
+        assert!(state.record(AssignmentType::Homework, &third).is_empty());
+        assert_eq!(state.diff(AssignmentType::Circular, &third).new.len(), 2);
+    }
+}
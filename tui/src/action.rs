@@ -10,15 +10,97 @@ pub enum Action {
     Resume,
     Quit,
     ClearScreen,
-    Error(String),
+    /// A message for the user, toasted and kept in the history. `retry` runs the failed
+    /// operation again.
+    Notice {
+        severity: Severity,
+        message: String,
+        retry: Option<Box<Action>>,
+    },
     Help,
+    // Keys bound in the config, handled by whichever component is taking keys.
+    SelectNext,
+    SelectPrevious,
+    SelectFirst,
+    SelectLast,
+    SelectNone,
+    ScrollDown,
+    ScrollUp,
+    PageDown,
+    PageUp,
+    Back,
+    OpenSelected,
+    OpenDetails,
+    Search,
+    EditFilter,
+    GroupBySubject,
+    CycleSort,
+    ToggleRelativeDates,
+    ToggleThisWeek,
+    OpenAttachments,
+    ToggleChanges,
+    ToggleSelect,
+    DownloadSelected,
+    CancelSelected,
+    OpenInViewer,
+    ToggleMessages,
+    RetryFailed,
+    /// Open the help overlay with these keys.
+    ShowHelp(Vec<crate::components::help::HelpSection>),
     Mode(crate::app::Mode),
     AssignmentType(client_core::AssignmentType),
-    AssignmentDetails(Option<String>),
+    AssignmentDetails(Option<client_core::AssignmentDetails>),
     ToggleDownloadPopup,
-    StartZathura { attachment: client_core::Attachment },
+    /// Open an attachment in its configured viewer, fetching it first if needed.
+    OpenAttachment {
+        attachment: client_core::Attachment,
+    },
     Attachments(Vec<client_core::Attachment>),
     Assignment(client_core::Assignment),
-    StartDownload(Vec<client_core::Attachment>),
+    /// Download `attachments` into `dir`, or into the folder of the current assignment.
+    StartDownload {
+        dir: Option<std::path::PathBuf>,
+        attachments: Vec<client_core::Attachment>,
+    },
+    /// `StartDownload` queued `attachment` as download `id`.
+    DownloadQueued {
+        id: client_core::download::DownloadId,
+        attachment: client_core::Attachment,
+    },
+    /// Progress of a download started by `StartDownload`.
+    Download(client_core::DownloadEvent),
+    CancelDownload(client_core::download::DownloadId),
     FinishDownload,
+    SessionExpired,
+    /// A background refresh of the listing for this query finished.
+    AssignmentsLoaded(client_core::AssignmentQuery, Vec<client_core::Assignment>),
+    /// Details fetched in the background for an assignment that had none cached.
+    DetailsLoaded(client_core::Assignment, client_core::AssignmentDetails),
+    /// The filter bar was applied once its subject name was looked up.
+    FilterResolved {
+        filter: String,
+        query: client_core::AssignmentQuery,
+    },
+    /// Show the details of an assignment, fetching them if they aren't cached.
+    OpenAssignment(client_core::Assignment),
+    /// A background refresh failed because the portal could not be reached.
+    Offline,
+}
+
+impl Action {
+    /// An error notice that can't be retried.
+    pub fn error(message: impl Into<String>) -> Self {
+        Action::Notice {
+            severity: Severity::Error,
+            message: message.into(),
+            retry: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
 }
@@ -1,19 +1,32 @@
-use std::io::Write;
-
-use client_core::AssignmentType;
+use client_core::{
+    Assignment, AssignmentQuery, AssignmentType, Attachment, Cache, DownloadEvent, DownloadHandle,
+    DownloadJob, Downloader, PortalClient, Session, download::DownloadId, login::Login,
+};
 use color_eyre::Result;
-use crossterm::event::KeyEvent;
+use crossterm::event::{KeyEvent, KeyModifiers};
 use ratatui::prelude::Rect;
 use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    process::Stdio,
+};
 use strum::{AsRefStr, EnumIter};
-use tempfile::NamedTempFile;
 use tokio::sync::mpsc;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use crate::{
-    action::Action,
-    components::{Component, details::Details, download_popup::Popup, home::Home, list::List},
-    config::Config,
+    action::{Action, Severity},
+    components::{
+        Component,
+        details::Details,
+        download_popup::Popup,
+        help::{Help, HelpSection},
+        home::Home,
+        list::List,
+        status::Status,
+    },
+    config::{Config, get_cache_dir, get_download_dir, get_session_path},
     tui::{Event, Tui},
 };
 
@@ -29,6 +42,19 @@ pub struct App {
     action_tx: mpsc::UnboundedSender<Action>,
     action_rx: mpsc::UnboundedReceiver<Action>,
     assignment_type: AssignmentType,
+    popup_visible: bool,
+    client: PortalClient,
+    /// Whether logging in again may save the credentials, as asked for with `--store-credentials`.
+    store_credentials: bool,
+    cache: Cache,
+    /// The assignment whose attachments are on screen, to pick their download folder.
+    assignment: Option<Assignment>,
+    downloader: Downloader,
+    download_events: mpsc::UnboundedReceiver<DownloadEvent>,
+    /// Downloads of the current batch that have not finished yet, with the folder they go to.
+    pending_downloads: HashMap<DownloadId, (DownloadHandle, Attachment, PathBuf)>,
+    /// Attachments being fetched into the cache to open them.
+    pending_views: HashSet<DownloadId>,
 }
 
 #[derive(
@@ -39,28 +65,56 @@ pub enum Mode {
     Home,
     ListScreen,
     CurrentAssignmentScreen,
+    /// Keys while the attachments popup is open over `CurrentAssignmentScreen`. Only used
+    /// to look up key bindings.
+    Attachments,
+    /// Keys while the help or the message history is open over any screen. Only used to look
+    /// up key bindings.
+    Overlay,
 }
 
 impl App {
-    pub fn new(tick_rate: f64, frame_rate: f64) -> Result<Self> {
+    pub fn new(
+        config: Config,
+        session: Session,
+        query: AssignmentQuery,
+        store_credentials: bool,
+        tick_rate: f64,
+        frame_rate: f64,
+    ) -> Result<Self> {
         let (action_tx, action_rx) = mpsc::unbounded_channel();
+        let cache = Cache::open(get_cache_dir(), session.student())?;
+        let client = PortalClient::new(config.portal.clone(), session)?
+            .with_session_file(get_session_path());
+        let (downloader, download_events) = Downloader::new(&client, config.downloads.concurrency);
         Ok(Self {
             tick_rate,
             frame_rate,
             components: vec![
                 Box::new(Home::new()),
-                Box::new(List::new()),
-                Box::new(Details::new()),
+                Box::new(List::new(client.clone(), cache.clone(), query)),
+                Box::new(Details::new(cache.clone())),
                 Box::new(Popup::new()),
+                Box::new(Status::new()),
+                Box::new(Help::new()),
             ],
             should_quit: false,
             should_suspend: false,
-            config: Config::new()?,
+            config,
             mode: Mode::Home,
             last_tick_key_events: Vec::new(),
             action_tx,
             action_rx,
             assignment_type: AssignmentType::default(),
+            popup_visible: false,
+            client,
+            store_credentials,
+            cache,
+            assignment: None,
+            downloader,
+            download_events,
+            pending_downloads: HashMap::new(),
+            pending_views: HashSet::new(),
         })
     }
 
@@ -105,15 +159,41 @@ impl App {
             return Ok(());
         };
         let action_tx = self.action_tx.clone();
+        let capturing = self
+            .components
+            .iter()
+            .position(|component| component.captures_keys());
+        let typing = self
+            .components
+            .iter()
+            .any(|component| component.takes_text_input());
         match event {
             Event::Quit => action_tx.send(Action::Quit)?,
             Event::Tick => action_tx.send(Action::Tick)?,
             Event::Render => action_tx.send(Action::Render)?,
             Event::Resize(x, y) => action_tx.send(Action::Resize(x, y))?,
-            Event::Key(key) => self.handle_key_event(key)?,
+            // Plain keys belong to the text field being typed into.
+            Event::Key(key)
+                if capturing.is_none()
+                    && (!typing
+                        || key
+                            .modifiers
+                            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)) =>
+            {
+                self.handle_key_event(key)?
+            }
             _ => {}
         }
-        for component in self.components.iter_mut() {
+        for (index, component) in self.components.iter_mut().enumerate() {
+            if let Event::Key(_) = event {
+                let skip = match capturing {
+                    Some(capturing) => capturing != index,
+                    None => typing && component.is_overlay(),
+                };
+                if skip {
+                    continue;
+                }
+            }
             if let Some(action) = component.handle_events(Some(event.clone()))? {
                 action_tx.send(action)?;
             }
@@ -121,9 +201,18 @@ impl App {
         Ok(())
     }
 
+    /// The mode whose key bindings are active.
+    fn key_mode(&self) -> Mode {
+        if self.mode == Mode::CurrentAssignmentScreen && self.popup_visible {
+            Mode::Attachments
+        } else {
+            self.mode
+        }
+    }
+
     fn handle_key_event(&mut self, key: KeyEvent) -> Result<()> {
         let action_tx = self.action_tx.clone();
-        let Some(keymap) = self.config.keybindings.get(&self.mode) else {
+        let Some(keymap) = self.config.keybindings.get(&self.key_mode()) else {
             return Ok(());
         };
 
@@ -149,6 +238,9 @@ impl App {
     }
 
     async fn handle_actions(&mut self, tui: &mut Tui) -> Result<()> {
+        while let Ok(event) = self.download_events.try_recv() {
+            self.action_tx.send(Action::Download(event))?;
+        }
         while let Ok(action) = self.action_rx.try_recv() {
             if action != Action::Tick && action != Action::Render {
                 debug!("{action:?}");
@@ -164,44 +256,147 @@ impl App {
                 Action::Resize(w, h) => self.handle_resize(tui, w, h)?,
                 Action::Render => self.render(tui)?,
                 Action::Mode(mode) => self.mode = mode,
+                Action::ToggleDownloadPopup => self.popup_visible = !self.popup_visible,
+                Action::Help => {
+                    let mut sections = vec![HelpSection::bindings(&self.config, self.key_mode())];
+                    sections.extend(
+                        self.components
+                            .iter()
+                            .filter(|component| {
+                                component.is_overlay() || component.get_mode() == self.mode
+                            })
+                            .filter_map(|component| component.key_help()),
+                    );
+                    self.action_tx.send(Action::ShowHelp(sections))?;
+                }
+                Action::Notice {
+                    severity,
+                    ref message,
+                    ..
+                } => match severity {
+                    Severity::Info => info!("{message}"),
+                    Severity::Warning => warn!("{message}"),
+                    Severity::Error => error!("{message}"),
+                },
                 Action::AssignmentType(type_) => self.assignment_type = type_,
-                Action::StartZathura { ref attachment } => {
-                    let url = &attachment.url;
-                    let Ok(res) = reqwest::get(url).await else {
-                        error!("Failed to download file: {:?}", url);
+                Action::Assignment(ref assignment) => self.assignment = Some(assignment.clone()),
+                Action::SessionExpired => {
+                    // The stored credentials no longer work, so ask for new ones outside the TUI.
+                    tui.exit()?;
+                    let session =
+                        Login::login(&self.config.portal, self.store_credentials, false).await;
+                    tui.enter()?;
+                    self.action_tx.send(Action::ClearScreen)?;
+                    match session {
+                        Ok(session) => {
+                            self.client.set_session(session);
+                            match (self.mode, &self.assignment) {
+                                (Mode::ListScreen, _) => self
+                                    .action_tx
+                                    .send(Action::AssignmentType(self.assignment_type))?,
+                                (Mode::CurrentAssignmentScreen, Some(assignment)) => self
+                                    .action_tx
+                                    .send(Action::OpenAssignment(assignment.clone()))?,
+                                _ => {}
+                            }
+                        }
+                        Err(err) => {
+                            error!("Failed to log in: {err:?}");
+                            self.action_tx.send(Action::Mode(Mode::Home))?;
+                        }
+                    }
+                }
+                Action::OpenAttachment { ref attachment } => {
+                    if let Some(path) = self.cache.attachment(attachment) {
+                        self.open_in_viewer(&path);
                         continue;
-                    };
-                    let mut file = NamedTempFile::new()?;
-                    let content = res.bytes().await;
-                    if let Ok(content) = content {
-                        file.write_all(&content).unwrap();
-                    } else {
-                        error!("Failed to download file: {:?}", url);
                     }
-
-                    let path = file.into_temp_path();
-                    let _ = std::process::Command::new("zathura")
-                        .stdout(std::process::Stdio::null())
-                        .arg(path.to_str().unwrap())
-                        .output()?;
+                    let path = self.cache.attachment_path(attachment);
+                    let handle = self.downloader.start(DownloadJob {
+                        url: attachment.url.clone(),
+                        dir: path.parent().unwrap_or(self.cache.dir()).to_path_buf(),
+                        name: attachment.name.clone(),
+                        replace: true,
+                    });
+                    self.pending_views.insert(handle.id);
                 }
-                Action::StartDownload(ref attachments) => {
+                Action::StartDownload {
+                    ref dir,
+                    ref attachments,
+                } => {
+                    let dir = dir.clone().unwrap_or_else(|| {
+                        self.config.downloads.target_dir(
+                            &get_download_dir(&self.config.downloads),
+                            self.client.session().student(),
+                            self.assignment.as_ref(),
+                        )
+                    });
                     for item in attachments {
-                        let url = &item.url;
-                        let name = &item.name;
-                        let Ok(res) = reqwest::get(url).await else {
-                            error!("Failed to download file: {:?}", url);
-                            continue;
-                        };
-                        let mut file = std::fs::File::create(name).unwrap();
-                        let content = res.bytes().await;
-                        if let Ok(content) = content {
-                            file.write_all(&content).unwrap();
-                        } else {
-                            error!("Failed to download file: {:?}", url);
+                        let handle = self.downloader.start(DownloadJob {
+                            url: item.url.clone(),
+                            dir: dir.clone(),
+                            name: item.name.clone(),
+                            replace: false,
+                        });
+                        self.action_tx.send(Action::DownloadQueued {
+                            id: handle.id,
+                            attachment: item.clone(),
+                        })?;
+                        self.pending_downloads
+                            .insert(handle.id, (handle, item.clone(), dir.clone()));
+                    }
+                    if self.pending_downloads.is_empty() {
+                        self.action_tx.send(Action::FinishDownload)?;
+                    }
+                }
+                Action::Download(ref event) => {
+                    let id = match event {
+                        DownloadEvent::Progress { .. } => None,
+                        DownloadEvent::Finished { id, path } if self.pending_views.remove(id) => {
+                            self.open_in_viewer(path);
+                            None
+                        }
+                        DownloadEvent::Failed { id, error } if self.pending_views.remove(id) => {
+                            self.action_tx.send(Action::error(format!(
+                                "Failed to fetch the attachment: {error}"
+                            )))?;
+                            None
+                        }
+                        DownloadEvent::Finished { id, path } => {
+                            info!("Saved {}", path.display());
+                            Some(*id)
+                        }
+                        DownloadEvent::Failed { id, error } => {
+                            if let Some((_, attachment, dir)) = self.pending_downloads.get(id) {
+                                self.action_tx.send(Action::Notice {
+                                    severity: Severity::Error,
+                                    message: format!(
+                                        "Failed to download {}: {error}",
+                                        attachment.name
+                                    ),
+                                    retry: Some(Box::new(Action::StartDownload {
+                                        dir: Some(dir.clone()),
+                                        attachments: vec![attachment.clone()],
+                                    })),
+                                })?;
+                            }
+                            Some(*id)
+                        }
+                    };
+                    if let Some(id) = id
+                        && self.pending_downloads.remove(&id).is_some()
+                        && self.pending_downloads.is_empty()
+                    {
+                        self.action_tx.send(Action::FinishDownload)?;
+                    }
+                }
+                Action::CancelDownload(id) => {
+                    if let Some((handle, ..)) = self.pending_downloads.remove(&id) {
+                        handle.cancel();
+                        if self.pending_downloads.is_empty() {
+                            self.action_tx.send(Action::FinishDownload)?;
                         }
                     }
-                    self.action_tx.send(Action::FinishDownload)?;
                 }
                 _ => {}
             }
@@ -214,6 +409,44 @@ impl App {
         Ok(())
     }
 
+    /// Opens `path` with the configured viewer without waiting for it to exit.
+    fn open_in_viewer(&self, path: &Path) {
+        let command = self.config.viewers.command(path);
+        let Some((program, args)) = command.split_first() else {
+            return;
+        };
+        let mut viewer = tokio::process::Command::new(program);
+        viewer
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        // Keep terminal signals such as Ctrl-C away from the viewer.
+        #[cfg(unix)]
+        viewer.process_group(0);
+        match viewer.spawn() {
+            Ok(mut child) => {
+                let program = program.clone();
+                let action_tx = self.action_tx.clone();
+                tokio::spawn(async move {
+                    let message = match child.wait().await {
+                        Ok(status) if !status.success() => {
+                            format!("{program} exited with {status}")
+                        }
+                        Ok(_) => return,
+                        Err(err) => format!("Failed to wait for {program}: {err}"),
+                    };
+                    let _ = action_tx.send(Action::error(message));
+                });
+            }
+            Err(err) => {
+                let _ = self
+                    .action_tx
+                    .send(Action::error(format!("Failed to start {program}: {err}")));
+            }
+        }
+    }
+
     fn handle_resize(&mut self, tui: &mut Tui, w: u16, h: u16) -> Result<()> {
         tui.resize(Rect::new(0, 0, w, h))?;
         self.render(tui)?;
@@ -223,12 +456,12 @@ impl App {
     fn render(&mut self, tui: &mut Tui) -> Result<()> {
         tui.draw(|frame| {
             for component in self.components.iter_mut() {
-                if self.mode == component.get_mode()
+                if (component.is_overlay() || self.mode == component.get_mode())
                     && let Err(err) = component.draw(frame, frame.area())
                 {
                     let _ = self
                         .action_tx
-                        .send(Action::Error(format!("Failed to draw: {:?}", err)));
+                        .send(Action::error(format!("Failed to draw: {:?}", err)));
                 }
             }
         })?;
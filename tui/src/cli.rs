@@ -1,4 +1,6 @@
+use chrono::NaiveDate;
 use clap::{Parser, Subcommand};
+use client_core::{AssignmentQuery, AssignmentType, PortalConfig};
 
 use crate::config::get_data_dir;
 
@@ -13,7 +15,7 @@ pub struct Cli {
     #[arg(long, value_name = "FLOAT", default_value_t = 60.0)]
     pub frame_rate: f64,
 
-    /// Whether to login or to use existing `env` variables
+    /// Whether to login instead of using the saved session or existing `env` variables
     #[arg(short, long)]
     pub login: bool,
 
@@ -25,12 +27,52 @@ pub struct Cli {
     #[arg(short, long, default_value_t = false)]
     pub fetch_credentials: bool,
 
+    /// Base URL of the CampusCare portal, overriding `portal.base_url` in the config file
+    #[arg(long, value_name = "URL")]
+    pub base_url: Option<String>,
+
+    /// School code sent in the `SchoolCode` cookie, overriding `portal.school_code`
+    #[arg(long, value_name = "CODE")]
+    pub school_code: Option<String>,
+
+    /// User type ID sent on login, overriding `portal.user_type_id`
+    #[arg(long, value_name = "ID")]
+    pub user_type_id: Option<String>,
+
+    /// Only list assignments issued on or after this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub from: Option<NaiveDate>,
+
+    /// Only list assignments issued on or before this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub to: Option<NaiveDate>,
+
+    /// Only list assignments for this subject, by ID or name
+    #[arg(long, value_name = "SUBJECT")]
+    pub subject: Option<String>,
+
     #[command(subcommand)]
     pub command: Option<Command>,
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Command {
+    /// Inspect or manage the saved login session
+    Session {
+        #[command(subcommand)]
+        command: SessionCommand,
+    },
+    /// List the subjects of the logged-in student
+    Subjects,
+    /// Fetch the listings and print what came in since the last sync
+    Sync {
+        /// Only sync this type (`H` for homework, `C` for circulars)
+        #[arg(short, long = "type")]
+        type_: Vec<AssignmentType>,
+        /// Also fetch every assignment's details to report ones that were edited
+        #[arg(long)]
+        details: bool,
+    },
     /// Generate shell completions
     Completions {
         /// The shell to generate completions for
@@ -39,6 +81,41 @@ pub enum Command {
     },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum SessionCommand {
+    /// Print the saved session
+    Show,
+    /// Check whether the portal still accepts the saved session
+    Validate,
+    /// Delete the saved session
+    Clear,
+}
+
+impl Cli {
+    /// The initial list filter given by `--from`, `--to` and `--subject`.
+    pub fn query(&self) -> AssignmentQuery {
+        AssignmentQuery {
+            from: self.from,
+            to: self.to,
+            subject: self.subject.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Applies the portal flags on top of the settings loaded from the config file.
+    pub fn apply_portal_overrides(&self, portal: &mut PortalConfig) {
+        if let Some(base_url) = &self.base_url {
+            portal.set_base_url(base_url);
+        }
+        if let Some(school_code) = &self.school_code {
+            portal.school_code = school_code.clone();
+        }
+        if let Some(user_type_id) = &self.user_type_id {
+            portal.user_type_id = user_type_id.clone();
+        }
+    }
+}
+
 const VERSION_MESSAGE: &str = concat!(
     env!("CARGO_PKG_VERSION"),
     "-",
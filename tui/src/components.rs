@@ -10,8 +10,10 @@ use crate::{action::Action, app::Mode, config::Config, tui::Event};
 
 pub mod details;
 pub mod download_popup;
+pub mod help;
 pub mod home;
 pub mod list;
+pub mod status;
 
 /// `Component` is a trait that represents a visual and interactive element of the user interface.
 ///
@@ -126,4 +128,21 @@ pub trait Component {
     fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()>;
     /// Gets the mode for which the component is rendered.
     fn get_mode(&self) -> Mode;
+    /// Whether the component is drawn on top of every mode rather than only its own.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+    /// Whether the component takes every key event, e.g. while a modal pane is open.
+    fn captures_keys(&self) -> bool {
+        false
+    }
+    /// Whether keys are being typed into a text field, so overlays should ignore them.
+    fn takes_text_input(&self) -> bool {
+        false
+    }
+    /// Keys the component handles in its current state, for the help overlay. `None` while
+    /// it isn't taking keys.
+    fn key_help(&self) -> Option<help::HelpSection> {
+        None
+    }
 }
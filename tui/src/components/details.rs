@@ -1,17 +1,22 @@
 use std::cmp::max;
 
-use client_core::Assignment;
+use client_core::{
+    Assignment, AssignmentDetails, Cache, DetailsVersion, LineChange, RichText, diff_lines,
+};
 use color_eyre::Result;
-use crossterm::event::KeyCode;
 use layout::Flex;
 use ratatui::{prelude::*, widgets::*};
+use tracing::warn;
 
-use style::palette::tailwind::SLATE;
 use tokio::sync::mpsc::UnboundedSender;
 use tui_scrollview::{ScrollView, ScrollViewState};
 
 use super::Component;
-use crate::{action::Action, app::Mode, config::Config};
+use crate::{
+    action::Action,
+    app::Mode,
+    config::{Config, Styles},
+};
 
 fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
     let [area] = Layout::horizontal([horizontal])
@@ -21,22 +26,126 @@ fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
     area
 }
 
+/// Converts the portal's rich text to styled ratatui text, with links in `details.link`.
+fn rich_text(text: &RichText, styles: &Styles) -> Text<'static> {
+    let lines = text.lines.iter().map(|line| {
+        Line::from(
+            line.spans
+                .iter()
+                .map(|span| {
+                    let mut style = Style::new();
+                    if span.style.bold {
+                        style = style.bold();
+                    }
+                    if span.style.italic {
+                        style = style.italic();
+                    }
+                    if span.style.underline {
+                        style = style.underlined();
+                    }
+                    if span.style.strikethrough {
+                        style = style.crossed_out();
+                    }
+                    if span.link.is_some() {
+                        style = style.patch(styles.style("details.link"));
+                    }
+                    Span::styled(span.text.clone(), style)
+                })
+                .collect::<Vec<_>>(),
+        )
+    });
+    Text::from_iter(lines)
+}
+
+/// Lays out the header fields, the body and the attachment names, with `download_key` as the
+/// hint for downloading them.
+fn details_text(
+    details: &AssignmentDetails,
+    download_key: Option<&str>,
+    styles: &Styles,
+) -> Text<'static> {
+    let field = |name: &str, value: &str| {
+        Line::from(vec![
+            Span::styled(format!("{name}: "), styles.style("details.label")),
+            Span::raw(value.to_string()),
+        ])
+    };
+    let mut lines = vec![Line::from(details.title.clone()).bold(), Line::default()];
+    if !details.subject.is_empty() {
+        lines.push(field("Subject", &details.subject));
+    }
+    if let Some(teacher) = &details.teacher {
+        lines.push(field("Teacher", teacher));
+    }
+    lines.push(field("Issued", &details.issue_date));
+    if let Some(due_date) = &details.due_date {
+        lines.push(field("Due", due_date));
+    }
+    lines.push(Line::default());
+    lines.extend(rich_text(&details.rich_body, styles).lines);
+    if !details.attachments.is_empty() {
+        lines.push(Line::default());
+        let heading = match download_key {
+            Some(key) => format!("Attachments (press `{key}` to download)"),
+            None => "Attachments".to_string(),
+        };
+        lines.push(Line::from(heading).bold());
+        lines.extend(
+            details
+                .attachments
+                .iter()
+                .map(|attachment| Line::from(format!("  {}", attachment.name))),
+        );
+    }
+    Text::from(lines)
+}
+
+/// Line diff from `previous` to `current`, styled as `details.removed` and `details.added`.
+fn changes_text(
+    previous: &DetailsVersion,
+    current: &AssignmentDetails,
+    styles: &Styles,
+) -> Text<'static> {
+    let seen = previous
+        .synced_at
+        .with_timezone(&chrono::Local)
+        .format("%d %b %H:%M");
+    let header = [
+        Line::from(format!("Changes since the version seen on {seen}")).bold(),
+        Line::default(),
+    ];
+    let changes = diff_lines(&previous.details.version_lines(), &current.version_lines());
+    let lines = changes.into_iter().map(|change| match change {
+        LineChange::Same(line) => Line::raw(format!("  {line}")),
+        LineChange::Added(line) => Line::styled(format!("+ {line}"), styles.style("details.added")),
+        LineChange::Removed(line) => {
+            Line::styled(format!("- {line}"), styles.style("details.removed"))
+        }
+    });
+    Text::from_iter(header.into_iter().chain(lines))
+}
+
 #[derive(Default)]
 pub struct Details {
     command_tx: Option<UnboundedSender<Action>>,
     config: Config,
     mode: Mode,
     enabled: bool,
-    current_assignment: Option<String>,
+    current_assignment: Option<AssignmentDetails>,
     assignment: Option<Assignment>,
     scrollview_state: ScrollViewState,
     popup_is_visible: bool,
+    cache: Option<Cache>,
+    /// Earlier versions of the shown details, oldest first.
+    previous_versions: Vec<DetailsVersion>,
+    show_changes: bool,
 }
 
 impl Details {
-    pub fn new() -> Self {
+    pub fn new(cache: Cache) -> Self {
         Details {
             mode: Mode::CurrentAssignmentScreen,
+            cache: Some(cache),
             enabled: true,
             popup_is_visible: false,
             ..Default::default()
@@ -44,6 +153,24 @@ impl Details {
     }
 }
 
+impl Details {
+    /// Cached versions of `details` other than the shown one, oldest first.
+    fn previous_versions(&self, details: &AssignmentDetails) -> Vec<DetailsVersion> {
+        let cache = self.cache.as_ref().unwrap();
+        let hash = details.content_hash();
+        match cache.history(&details.id) {
+            Ok(history) => history
+                .into_iter()
+                .filter(|version| version.hash != hash)
+                .collect(),
+            Err(err) => {
+                warn!("Ignoring the details history: {err}");
+                vec![]
+            }
+        }
+    }
+}
+
 impl Component for Details {
     fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
         self.command_tx = Some(tx);
@@ -64,8 +191,21 @@ impl Component for Details {
             Action::Mode(mode) => {
                 self.enabled = mode == self.mode;
             }
-            Action::AssignmentDetails(assignment) => {
-                self.current_assignment = assignment;
+            Action::AssignmentDetails(details) => {
+                // A background refresh may finish after another assignment was opened.
+                let is_current = |details: &AssignmentDetails| {
+                    self.assignment
+                        .as_ref()
+                        .is_none_or(|assignment| assignment.id == details.id)
+                };
+                if details.as_ref().is_none_or(is_current) {
+                    self.previous_versions = details
+                        .as_ref()
+                        .map(|details| self.previous_versions(details))
+                        .unwrap_or_default();
+                    self.show_changes &= !self.previous_versions.is_empty();
+                    self.current_assignment = details;
+                }
             }
 
             Action::ToggleDownloadPopup => {
@@ -74,66 +214,74 @@ impl Component for Details {
             Action::Assignment(assignment) => {
                 self.assignment = Some(assignment);
             }
-            _ => {}
-        }
-        Ok(None)
-    }
-    fn handle_key_event(&mut self, key: crossterm::event::KeyEvent) -> Result<Option<Action>> {
-        if !self.enabled || self.popup_is_visible {
-            return Ok(None);
-        }
-        match key.code {
-            KeyCode::Char('q') => return Ok(Some(Action::Quit)),
-            KeyCode::Char('j') | KeyCode::Down => self.scrollview_state.scroll_down(),
-            KeyCode::Char('k') | KeyCode::Up => self.scrollview_state.scroll_up(),
-            KeyCode::Char('f') | KeyCode::PageDown => self.scrollview_state.scroll_page_down(),
-            KeyCode::Char('b') | KeyCode::PageUp => self.scrollview_state.scroll_page_up(),
-            KeyCode::Char('d') => {
-                if let Some(assignment) = &self.assignment {
-                    let attachments =
-                        futures::executor::block_on(assignment.get_attachments(assignment));
-                    if let Ok(attachments) = attachments {
-                        self.command_tx
-                            .clone()
-                            .unwrap()
-                            .send(Action::Attachments(attachments))?;
-                        self.command_tx
-                            .clone()
-                            .unwrap()
-                            .send(Action::ToggleDownloadPopup)?;
-                    }
+            _ if !self.enabled || self.popup_is_visible => {}
+            Action::ScrollDown => self.scrollview_state.scroll_down(),
+            Action::ScrollUp => self.scrollview_state.scroll_up(),
+            Action::PageDown => self.scrollview_state.scroll_page_down(),
+            Action::PageUp => self.scrollview_state.scroll_page_up(),
+            Action::ToggleChanges if !self.previous_versions.is_empty() => {
+                self.show_changes = !self.show_changes;
+                self.scrollview_state.scroll_to_top();
+            }
+            Action::OpenAttachments => {
+                if let Some(details) = &self.current_assignment {
+                    self.command_tx
+                        .clone()
+                        .unwrap()
+                        .send(Action::Attachments(details.attachments.clone()))?;
+                    return Ok(Some(Action::ToggleDownloadPopup));
                 }
-                return Ok(None);
             }
-            KeyCode::Esc => {
+            Action::Back => {
                 self.command_tx.clone().unwrap().send(Action::ClearScreen)?;
                 return Ok(Some(Action::Mode(Mode::ListScreen)));
             }
             _ => {}
-        };
+        }
         Ok(None)
     }
 
     fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
         let centered = center(area, Constraint::Percentage(50), Constraint::Percentage(50));
-        let assignment = self.current_assignment.clone().unwrap_or_default();
+        let keys = &self.config.keybindings;
+        let styles = &self.config.styles;
+        let text = match (&self.current_assignment, self.previous_versions.last()) {
+            (Some(details), Some(previous)) if self.show_changes => {
+                changes_text(previous, details, styles)
+            }
+            (Some(details), _) => details_text(
+                details,
+                keys.key_for(self.mode, &Action::OpenAttachments).as_deref(),
+                styles,
+            ),
+            (None, _) => Text::default(),
+        };
+        let mut title = vec![Span::raw("Assignment Details")];
+        if !self.previous_versions.is_empty() {
+            let verb = if self.show_changes { "hide" } else { "show" };
+            let hint = match keys.key_for(self.mode, &Action::ToggleChanges) {
+                Some(key) => format!(" · Revised (`{key}` to {verb} changes)"),
+                None => " · Revised".to_string(),
+            };
+            title.push(Span::styled(hint, styles.style("details.revised")));
+        }
         let size = Size::new(
             centered.width,
-            max(assignment.lines().count() as u16, centered.height),
+            max(text.lines.len() as u16, centered.height),
         );
         let mut scrollview = ScrollView::new(size)
             .horizontal_scrollbar_visibility(tui_scrollview::ScrollbarVisibility::Never);
-        let para = Paragraph::new(assignment)
+        let para = Paragraph::new(text)
             .style(Style::default())
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title("Assignment Details")
+                    .title(Line::from(title))
                     .padding(Padding::uniform(1))
                     .border_type(BorderType::Rounded)
-                    .border_style(Style::default().fg(SLATE.c500)),
+                    .border_style(styles.style("details.border")),
             )
-            .wrap(Wrap { trim: true });
+            .wrap(Wrap { trim: false });
 
         scrollview.render_widget(para, scrollview.area());
         frame.render_stateful_widget(scrollview, centered, &mut self.scrollview_state);
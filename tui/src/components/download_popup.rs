@@ -1,17 +1,17 @@
-use client_core::Attachment;
+use client_core::{Attachment, DownloadEvent, download::DownloadId};
 use color_eyre::Result;
-use crossterm::event::KeyCode;
 use itertools::Itertools;
 use layout::Flex;
 use ratatui::{prelude::*, widgets::*};
-use style::palette::tailwind::SLATE;
 use tokio::sync::mpsc::UnboundedSender;
 use tracing::info;
 
 use super::Component;
-use crate::{action::Action, app::Mode, config::Config};
-
-const SELECTED_STYLE: Style = Style::new().bg(SLATE.c800).add_modifier(Modifier::BOLD);
+use crate::{
+    action::Action,
+    app::Mode,
+    config::{Config, FALLBACK_VIEWER, Styles},
+};
 
 fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
     let [area] = Layout::horizontal([horizontal])
@@ -41,6 +41,29 @@ pub struct AttachmentList {
 pub struct AttachmentListItem {
     attachment: Attachment,
     selected: bool,
+    /// The latest download of this attachment.
+    download: Option<DownloadId>,
+    state: DownloadState,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+enum DownloadState {
+    #[default]
+    Idle,
+    Queued,
+    Running {
+        received: u64,
+        total: Option<u64>,
+    },
+    Saved(String),
+    Failed(String),
+    Cancelled,
+}
+
+impl DownloadState {
+    fn in_flight(&self) -> bool {
+        matches!(self, DownloadState::Queued | DownloadState::Running { .. })
+    }
 }
 
 impl Popup {
@@ -77,90 +100,126 @@ impl Component for Popup {
             Action::ToggleDownloadPopup => {
                 self.visible = !self.visible;
             }
-            Action::Attachments(attachments) => {
-                self.list =
-                    AttachmentList::from_iter(attachments.into_iter().map(AttachmentListItem::new));
+            Action::Attachments(attachments) => self.list.merge(attachments),
+            Action::DownloadQueued { id, attachment } => {
+                if let Some(item) = self.list.item_for(&attachment) {
+                    item.download = Some(id);
+                    item.state = DownloadState::Queued;
+                }
             }
-            Action::FinishDownload => {
-                self.command_tx
-                    .clone()
-                    .unwrap()
-                    .send(Action::ToggleDownloadPopup)?;
+            Action::Download(event) => {
+                let (id, state) = match event {
+                    DownloadEvent::Progress {
+                        id,
+                        received,
+                        total,
+                    } => (id, DownloadState::Running { received, total }),
+                    DownloadEvent::Finished { id, path } => {
+                        (id, DownloadState::Saved(path.display().to_string()))
+                    }
+                    DownloadEvent::Failed { id, error } => (id, DownloadState::Failed(error)),
+                };
+                if let Some(item) = self
+                    .list
+                    .list_items
+                    .iter_mut()
+                    .find(|item| item.download == Some(id) && item.state.in_flight())
+                {
+                    item.state = state;
+                }
             }
-            _ => {}
-        }
-        Ok(None)
-    }
-    fn handle_key_event(&mut self, key: crossterm::event::KeyEvent) -> Result<Option<Action>> {
-        info!("Got key: {key:?}");
-
-        if !self.enabled || !self.visible {
-            return Ok(None);
-        }
-        match key.code {
-            KeyCode::Char('q') => return Ok(Some(Action::Quit)),
-            KeyCode::Char('h') | KeyCode::Left => self.list.select_none(),
-            KeyCode::Char('j') | KeyCode::Down => self.list.select_next(),
-            KeyCode::Char('k') | KeyCode::Up => self.list.select_previous(),
-            KeyCode::Char('g') | KeyCode::Home => self.list.select_first(),
-            KeyCode::Char('G') | KeyCode::End => self.list.select_last(),
-            KeyCode::Char(' ') => {
-                let idx = self.list.state.selected().unwrap();
-                let mut item = self.list.list_items[idx].clone();
-                item.selected = !item.selected;
-                self.list.list_items[idx] = item;
-                return Ok(None);
+            _ if !self.enabled || !self.visible => {}
+            Action::SelectNone => self.list.select_none(),
+            Action::SelectNext => self.list.select_next(),
+            Action::SelectPrevious => self.list.select_previous(),
+            Action::SelectFirst => self.list.select_first(),
+            Action::SelectLast => self.list.select_last(),
+            Action::ToggleSelect => {
+                if let Some(item) = self
+                    .list
+                    .state
+                    .selected()
+                    .and_then(|idx| self.list.list_items.get_mut(idx))
+                {
+                    item.selected = !item.selected;
+                }
+            }
+            Action::OpenInViewer => {
+                let Some(item) = self
+                    .list
+                    .state
+                    .selected()
+                    .and_then(|idx| self.list.list_items.get(idx))
+                else {
+                    return Ok(None);
+                };
+                return Ok(Some(Action::OpenAttachment {
+                    attachment: item.attachment.clone(),
+                }));
             }
-            KeyCode::Char('o') => {
-                let idx = self.list.state.selected().unwrap();
-                let item = self.list.list_items[idx].clone();
-                self.command_tx
-                    .as_ref()
-                    .unwrap()
-                    .send(Action::StartZathura {
-                        attachment: item.attachment,
-                    })?;
-                return Ok(None);
+            Action::CancelSelected => {
+                let Some(item) = self
+                    .list
+                    .state
+                    .selected()
+                    .and_then(|idx| self.list.list_items.get_mut(idx))
+                else {
+                    return Ok(None);
+                };
+                if let Some(id) = item.download
+                    && item.state.in_flight()
+                {
+                    item.state = DownloadState::Cancelled;
+                    return Ok(Some(Action::CancelDownload(id)));
+                }
             }
-            KeyCode::Enter => {
+            Action::DownloadSelected => {
                 info!("Starting Download");
                 let selected = self
                     .list
                     .list_items
                     .iter()
-                    .filter(|item| item.selected)
+                    .filter(|item| item.selected && !item.state.in_flight())
+                    .map(|item| item.attachment.clone())
                     .collect_vec();
-                self.command_tx
-                    .as_ref()
-                    .unwrap()
-                    .send(Action::StartDownload(
-                        selected
-                            .iter()
-                            .map(|item| item.attachment.clone())
-                            .collect(),
-                    ))?;
-            }
-            KeyCode::Esc => {
-                self.command_tx
-                    .clone()
-                    .unwrap()
-                    .send(Action::ToggleDownloadPopup)?;
-                return Ok(None);
+                if !selected.is_empty() {
+                    return Ok(Some(Action::StartDownload {
+                        dir: None,
+                        attachments: selected,
+                    }));
+                }
             }
+            Action::Back => return Ok(Some(Action::ToggleDownloadPopup)),
             _ => {}
-        };
+        }
         Ok(None)
     }
 
     fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
-        let centered = center(area, Constraint::Percentage(30), Constraint::Percentage(30));
+        let centered = center(area, Constraint::Percentage(50), Constraint::Percentage(40));
         let items = self
             .list
             .list_items
             .iter()
-            .map(ListItem::from)
+            .map(|item| item.list_item(&self.config.styles))
             .collect_vec();
-        let text_btm = "<space> to select, <enter> to download, <o> to open with zathura";
+        let viewer = self
+            .list
+            .state
+            .selected()
+            .and_then(|idx| self.list.list_items.get(idx))
+            .map(|item| self.config.viewers.program(&item.attachment.name))
+            .unwrap_or_else(|| FALLBACK_VIEWER.to_string());
+        let open = format!("open with {viewer}");
+        let text_btm = self.config.keybindings.hints(
+            Mode::Attachments,
+            &[
+                (Action::ToggleSelect, "select"),
+                (Action::DownloadSelected, "download"),
+                (Action::CancelSelected, "cancel"),
+                (Action::OpenInViewer, &open),
+            ],
+        );
         let list_block = Block::new()
             .borders(Borders::ALL)
             .padding(Padding::uniform(1))
@@ -168,7 +227,7 @@ impl Component for Popup {
             .title_top(Line::raw("Attachments").centered().bold())
             .title_bottom(Line::raw(text_btm).centered());
         let list = List::new(items)
-            .highlight_style(SELECTED_STYLE)
+            .highlight_style(self.config.styles.style("popup.selected"))
             .highlight_symbol("> ")
             .highlight_spacing(HighlightSpacing::Always)
             .block(list_block);
@@ -184,6 +243,40 @@ impl Component for Popup {
 }
 
 impl AttachmentList {
+    /// Replaces the items with `attachments`, keeping the selection and download of those that
+    /// were already listed.
+    fn merge(&mut self, attachments: Vec<Attachment>) {
+        let mut previous = std::mem::take(&mut self.list_items);
+        self.list_items = attachments
+            .into_iter()
+            .map(|attachment| {
+                match previous
+                    .iter()
+                    .position(|item| item.attachment.url == attachment.url)
+                {
+                    Some(index) => AttachmentListItem {
+                        attachment,
+                        ..previous.swap_remove(index)
+                    },
+                    None => AttachmentListItem::new(attachment),
+                }
+            })
+            .collect();
+        let len = self.list_items.len();
+        self.state.select(
+            self.state
+                .selected()
+                .filter(|_| len > 0)
+                .map(|index| index.min(len - 1)),
+        );
+    }
+
+    fn item_for(&mut self, attachment: &Attachment) -> Option<&mut AttachmentListItem> {
+        self.list_items
+            .iter_mut()
+            .find(|item| item.attachment == *attachment)
+    }
+
     fn select_none(&mut self) {
         self.state.select(None);
     }
@@ -210,35 +303,93 @@ impl<'a> AttachmentListItem {
         Self {
             attachment,
             selected: false,
+            download: None,
+            state: DownloadState::Idle,
         }
     }
+    fn list_item(&self, styles: &Styles) -> ListItem<'a> {
+        let mut lines = vec![self.format(styles)];
+        lines.extend(self.status(styles));
+        ListItem::new(lines)
+    }
+
     #[allow(clippy::needless_lifetimes)]
-    fn format(&self) -> Line<'a> {
-        let selected_span = Span::styled(
-            if self.selected { "[x]" } else { "[ ]" }.to_string() + "  ",
-            if self.selected {
-                Style::default().fg(Color::Green)
-            } else {
-                Style::default()
-                    .add_modifier(Modifier::DIM)
-                    .fg(Color::White)
-            },
-        );
-        let text = Span::raw(self.attachment.name.clone());
-        let line_style = if self.selected {
-            Style::default().add_modifier(Modifier::BOLD)
+    fn format(&self, styles: &Styles) -> Line<'a> {
+        let (checkbox, checkbox_style, line_style) = if self.selected {
+            ("[x]", "popup.checked", "popup.item.checked")
         } else {
-            Style::default().add_modifier(Modifier::DIM)
+            ("[ ]", "popup.unchecked", "popup.item")
+        };
+        let selected_span = Span::styled(format!("{checkbox}  "), styles.style(checkbox_style));
+        let text = Span::raw(self.attachment.name.clone());
+        Line::from(vec![selected_span, text]).style(styles.style(line_style))
+    }
+
+    /// A second line with the progress or outcome of the download, if there is one.
+    fn status(&self, styles: &Styles) -> Option<Line<'a>> {
+        let indent = Span::raw("     ");
+        let muted = styles.style("popup.muted");
+        let line = match &self.state {
+            DownloadState::Idle => return None,
+            DownloadState::Queued => Line::from(vec![indent, Span::styled("Queued", muted)]),
+            DownloadState::Running { received, total } => {
+                let mut spans = vec![indent];
+                if let Some(total) = total.filter(|total| *total > 0) {
+                    let ratio = (*received as f64 / total as f64).min(1.0);
+                    spans.push(Span::styled(
+                        gauge(ratio, GAUGE_WIDTH),
+                        styles.style("popup.progress"),
+                    ));
+                    spans.push(Span::raw(format!(
+                        " {:>3}%  {} / {}",
+                        (ratio * 100.0) as u8,
+                        format_size(*received),
+                        format_size(total)
+                    )));
+                } else {
+                    spans.push(Span::raw(format_size(*received)));
+                }
+                Line::from(spans)
+            }
+            DownloadState::Saved(path) => Line::from(vec![
+                indent,
+                Span::styled("✓ ", styles.style("popup.saved")),
+                Span::styled(format!("Saved to {path}"), muted),
+            ]),
+            DownloadState::Failed(error) => Line::from(vec![
+                indent,
+                Span::styled(format!("✗ {error}"), styles.style("popup.failed")),
+            ]),
+            DownloadState::Cancelled => Line::from(vec![
+                indent,
+                Span::styled("Cancelled", styles.style("popup.cancelled")),
+            ]),
         };
-        Line::from(vec![selected_span, text]).style(line_style)
+        Some(line)
     }
 }
 
-#[allow(clippy::needless_lifetimes)]
-impl<'a> From<&AttachmentListItem> for ListItem<'a> {
-    fn from(value: &AttachmentListItem) -> Self {
-        ListItem::new(value.format())
+const GAUGE_WIDTH: usize = 20;
+
+/// A text progress bar `width` cells wide, filled to `ratio`.
+fn gauge(ratio: f64, width: usize) -> String {
+    let filled = (ratio.clamp(0.0, 1.0) * width as f64).round() as usize;
+    format!("{}{}", "█".repeat(filled), "░".repeat(width - filled))
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 MiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
     }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
 }
 
 impl FromIterator<AttachmentListItem> for AttachmentList {
@@ -251,3 +402,51 @@ impl FromIterator<AttachmentListItem> for AttachmentList {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(25 * 1024 * 1024), "25.0 MiB");
+    }
+
+    #[test]
+    fn test_merge_keeps_downloads() {
+        let attachment = |name: &str| Attachment {
+            name: name.to_string(),
+            url: format!("https://portal/{name}"),
+        };
+        let mut list = AttachmentList::from_iter(
+            [attachment("a.pdf"), attachment("b.pdf")].map(AttachmentListItem::new),
+        );
+        list.list_items[1].selected = true;
+        list.list_items[1].download = Some(7);
+        list.list_items[1].state = DownloadState::Running {
+            received: 10,
+            total: None,
+        };
+        list.state.select(Some(1));
+
+        list.merge(vec![attachment("b.pdf")]);
+        assert_eq!(list.list_items.len(), 1);
+        let item = &list.list_items[0];
+        assert!(item.selected);
+        assert_eq!(item.download, Some(7));
+        assert!(item.state.in_flight());
+        assert_eq!(list.state.selected(), Some(0));
+
+        list.merge(vec![]);
+        assert_eq!(list.state.selected(), None);
+    }
+
+    #[test]
+    fn test_gauge() {
+        assert_eq!(gauge(0.0, 4), "░░░░");
+        assert_eq!(gauge(0.5, 4), "██░░");
+        assert_eq!(gauge(1.5, 4), "████");
+    }
+}
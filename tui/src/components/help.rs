@@ -0,0 +1,256 @@
+use color_eyre::Result;
+use crossterm::event::KeyEvent;
+use layout::Flex;
+use ratatui::{prelude::*, widgets::*};
+use serde::{Deserialize, Serialize};
+
+use super::Component;
+use crate::{
+    action::Action,
+    app::Mode,
+    config::{Config, key_event_to_string},
+};
+
+/// A group of keys in the help overlay.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HelpSection {
+    pub title: String,
+    /// Keys and what they do.
+    pub keys: Vec<(String, String)>,
+}
+
+impl HelpSection {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            keys: vec![],
+        }
+    }
+
+    pub fn key(mut self, keys: impl Into<String>, description: impl Into<String>) -> Self {
+        self.keys.push((keys.into(), description.into()));
+        self
+    }
+
+    /// The bindings of `mode`, grouped by action.
+    pub fn bindings(config: &Config, mode: Mode) -> Self {
+        let title = match mode {
+            Mode::Home => "Modes",
+            Mode::ListScreen => "Assignments",
+            Mode::CurrentAssignmentScreen => "Assignment details",
+            Mode::Attachments => "Attachments",
+            Mode::Overlay => "Help and messages",
+        };
+        let mut section = Self::new(title);
+        let Some(bindings) = config.keybindings.get(&mode) else {
+            return section;
+        };
+        let mut by_action: Vec<(String, Vec<String>)> = vec![];
+        for (keys, action) in bindings {
+            let keys = keys.iter().map(key_event_to_string).collect::<Vec<_>>();
+            let description = describe_action(action);
+            match by_action
+                .iter_mut()
+                .find(|(other, _)| *other == description)
+            {
+                Some((_, all_keys)) => all_keys.push(keys.join(" ")),
+                None => by_action.push((description, vec![keys.join(" ")])),
+            }
+        }
+        by_action.sort();
+        for (description, mut keys) in by_action {
+            keys.sort();
+            section.keys.push((keys.join(", "), description));
+        }
+        section
+    }
+}
+
+/// What a bound action does, for the help overlay.
+fn describe_action(action: &Action) -> String {
+    match action {
+        Action::Quit => "Quit".to_string(),
+        Action::Suspend => "Suspend to the shell".to_string(),
+        Action::Help => "Show this help".to_string(),
+        Action::ClearScreen => "Redraw the screen".to_string(),
+        Action::SelectNext => "Move down".to_string(),
+        Action::SelectPrevious => "Move up".to_string(),
+        Action::SelectFirst => "Go to the first item".to_string(),
+        Action::SelectLast => "Go to the last item".to_string(),
+        Action::SelectNone => "Clear the selection".to_string(),
+        Action::ScrollDown => "Scroll down".to_string(),
+        Action::ScrollUp => "Scroll up".to_string(),
+        Action::PageDown => "Scroll a page down".to_string(),
+        Action::PageUp => "Scroll a page up".to_string(),
+        Action::Back => "Go back, or cancel loading".to_string(),
+        Action::OpenSelected => "Open".to_string(),
+        Action::OpenDetails => "Show details".to_string(),
+        Action::Search => "Search".to_string(),
+        Action::EditFilter => "Filter by date or subject".to_string(),
+        Action::GroupBySubject => "Group by subject".to_string(),
+        Action::CycleSort => "Cycle the date sort".to_string(),
+        Action::ToggleRelativeDates => "Toggle relative dates".to_string(),
+        Action::ToggleThisWeek => "Toggle this week only".to_string(),
+        Action::OpenAttachments => "Attachments".to_string(),
+        Action::ToggleChanges => "Show or hide changes since the last version".to_string(),
+        Action::ToggleSelect => "Select for download".to_string(),
+        Action::DownloadSelected => "Download the selected files".to_string(),
+        Action::CancelSelected => "Cancel the download under the cursor".to_string(),
+        Action::OpenInViewer => "Open in the viewer".to_string(),
+        Action::ToggleMessages => "Show or hide past messages".to_string(),
+        Action::RetryFailed => "Retry the last failed operation".to_string(),
+        action => action.to_string(),
+    }
+}
+
+/// Lists the keys active in the current mode and state, opened with [`Action::Help`].
+#[derive(Default)]
+pub struct Help {
+    config: Config,
+    sections: Vec<HelpSection>,
+    visible: bool,
+    scroll: usize,
+}
+
+impl Help {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lines(&self) -> Vec<Line<'static>> {
+        let width = self
+            .sections
+            .iter()
+            .flat_map(|section| &section.keys)
+            .map(|(keys, _)| keys.chars().count())
+            .max()
+            .unwrap_or_default();
+        let key_style = self.config.styles.style("help.key");
+        let mut lines = vec![];
+        for section in self
+            .sections
+            .iter()
+            .filter(|section| !section.keys.is_empty())
+        {
+            if !lines.is_empty() {
+                lines.push(Line::default());
+            }
+            lines.push(Line::raw(section.title.clone()).bold());
+            for (keys, description) in &section.keys {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {keys:<width$}  "), key_style),
+                    Span::raw(description.clone()),
+                ]));
+            }
+        }
+        lines
+    }
+}
+
+impl Component for Help {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.config = config;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action) -> Result<Option<Action>> {
+        if let Action::ShowHelp(sections) = action {
+            self.sections = sections;
+            self.visible = true;
+            self.scroll = 0;
+        }
+        Ok(None)
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        if !self.visible {
+            return Ok(None);
+        }
+        match self.config.keybindings.action(Mode::Overlay, key) {
+            Some(Action::Back | Action::Help) => self.visible = false,
+            Some(Action::ScrollDown) => self.scroll = self.scroll.saturating_add(1),
+            Some(Action::ScrollUp) => self.scroll = self.scroll.saturating_sub(1),
+            Some(Action::SelectFirst) => self.scroll = 0,
+            Some(Action::SelectLast) => self.scroll = usize::MAX,
+            Some(Action::Quit) => return Ok(Some(Action::Quit)),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        let lines = self.lines();
+        let [pane] = Layout::horizontal([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [pane] = Layout::vertical([Constraint::Length(lines.len() as u16 + 2)])
+            .flex(Flex::Center)
+            .areas(pane);
+        let visible = pane.height.saturating_sub(2) as usize;
+        self.scroll = self.scroll.min(lines.len().saturating_sub(visible));
+        let hints = self.config.keybindings.hints(
+            Mode::Overlay,
+            &[(Action::Back, "close"), (Action::ScrollDown, "scroll")],
+        );
+        let help = Paragraph::new(lines).scroll((self.scroll as u16, 0)).block(
+            Block::bordered()
+                .border_type(BorderType::Rounded)
+                .padding(Padding::horizontal(1))
+                .title_top(Line::raw("Keys").centered().bold())
+                .title_bottom(Line::raw(hints).centered()),
+        );
+        frame.render_widget(Clear, pane);
+        frame.render_widget(help, pane);
+        Ok(())
+    }
+
+    fn get_mode(&self) -> Mode {
+        Mode::Home
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+
+    fn captures_keys(&self) -> bool {
+        self.visible
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::config::parse_key_sequence;
+
+    #[test]
+    fn test_bindings_group_keys_by_action() {
+        let mut config = Config::default();
+        let bindings = [
+            ("<q>", Action::Quit),
+            ("<Ctrl-c>", Action::Quit),
+            ("<?>", Action::Help),
+        ]
+        .into_iter()
+        .map(|(keys, action)| (parse_key_sequence(keys).unwrap(), action))
+        .collect::<HashMap<_, _>>();
+        config.keybindings.insert(Mode::Home, bindings);
+        let section = HelpSection::bindings(&config, Mode::Home);
+        assert_eq!(
+            section.keys,
+            [
+                ("ctrl-c, q".to_string(), "Quit".to_string()),
+                ("?".to_string(), "Show this help".to_string()),
+            ]
+        );
+        assert!(
+            HelpSection::bindings(&config, Mode::ListScreen)
+                .keys
+                .is_empty()
+        );
+    }
+}
@@ -1,18 +1,13 @@
 use client_core::AssignmentType;
 use color_eyre::Result;
-use crossterm::event::KeyCode;
 use layout::Flex;
 use ratatui::{prelude::*, widgets::*};
-
-use style::palette::tailwind::SLATE;
 use tokio::sync::mpsc::UnboundedSender;
 use tracing::info;
 
 use super::Component;
 use crate::{action::Action, app::Mode, config::Config};
 
-const SELECTED_STYLE: Style = Style::new().bg(SLATE.c800).add_modifier(Modifier::BOLD);
-
 fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
     let [area] = Layout::horizontal([horizontal])
         .flex(Flex::Center)
@@ -101,45 +96,36 @@ impl Component for Home {
             Action::Mode(mode) => {
                 self.enabled = mode == self.mode;
             }
-            _ => {}
-        }
-        Ok(None)
-    }
-    fn handle_key_event(&mut self, key: crossterm::event::KeyEvent) -> Result<Option<Action>> {
-        info!("Got key: {key:?}");
-
-        if !self.enabled {
-            return Ok(None);
-        }
-        match key.code {
-            KeyCode::Char('h') | KeyCode::Left => self.list.select_none(),
-            KeyCode::Char('j') | KeyCode::Down => self.list.select_next(),
-            KeyCode::Char('k') | KeyCode::Up => self.list.select_previous(),
-            KeyCode::Char('g') | KeyCode::Home => self.list.select_first(),
-            KeyCode::Char('G') | KeyCode::End => self.list.select_last(),
-            KeyCode::Enter => {
-                self.command_tx
-                    .as_ref()
-                    .unwrap()
-                    .send(Action::AssignmentType(
-                        self.list.list_items[self.list.state.selected().unwrap_or(0)].mode,
-                    ))?;
+            _ if !self.enabled => {}
+            Action::SelectNone => self.list.select_none(),
+            Action::SelectNext => self.list.select_next(),
+            Action::SelectPrevious => self.list.select_previous(),
+            Action::SelectFirst => self.list.select_first(),
+            Action::SelectLast => self.list.select_last(),
+            Action::OpenSelected => {
+                info!("Opening {:?}", self.list.state.selected());
+                let Some(item) = self
+                    .list
+                    .list_items
+                    .get(self.list.state.selected().unwrap_or(0))
+                else {
+                    return Ok(None);
+                };
                 self.command_tx
                     .as_ref()
                     .unwrap()
-                    .send(Action::Mode(Mode::ListScreen))?;
+                    .send(Action::AssignmentType(item.mode))?;
                 return Ok(Some(Action::Mode(Mode::ListScreen)));
             }
-            KeyCode::Char('q') => return Ok(Some(Action::Quit)),
             _ => {}
-        };
+        }
         Ok(None)
     }
 
     fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
         let items: Vec<ListItem> = self.list.list_items.iter().map(ListItem::from).collect();
         let list = List::new(items)
-            .highlight_style(SELECTED_STYLE)
+            .highlight_style(self.config.styles.style("home.selected"))
             .highlight_symbol(">")
             .highlight_spacing(HighlightSpacing::Always)
             .scroll_padding(5)
@@ -1,22 +1,31 @@
-use client_core::{Assignment, get_circular, homework};
+use chrono::{DateTime, Datelike, Local, NaiveDate, Utc};
+use client_core::{
+    Assignment, AssignmentDetails, AssignmentQuery, Cache, PortalClient, PortalError, SyncState,
+};
 use color_eyre::Result;
 use crossterm::event::KeyCode;
-use futures::executor::block_on;
 use itertools::Itertools;
 use nucleo_matcher::pattern::{AtomKind, CaseMatching, Normalization, Pattern};
 use nucleo_matcher::{Config as NucleoConfig, Matcher};
 use ratatui::widgets::List as ListWidget;
 use ratatui::{prelude::*, widgets::*};
+use std::collections::HashSet;
 use std::io::Write;
-use style::palette::tailwind::SLATE;
+use std::path::PathBuf;
 use tabwriter::TabWriter;
-use tokio::sync::mpsc::UnboundedSender;
+use tokio::{sync::mpsc::UnboundedSender, task::AbortHandle};
+use tracing::warn;
 use tui_input::{Input, backend::crossterm::EventHandler};
 
-use super::Component;
-use crate::{action::Action, app::Mode, config::Config};
+use super::{Component, help::HelpSection};
+use crate::{
+    action::{Action, Severity},
+    app::Mode,
+    config::{Config, Styles},
+    theme::SUBJECT_STYLES,
+};
 
-const SELECTED_STYLE: Style = Style::new().bg(SLATE.c800).add_modifier(Modifier::BOLD);
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 
 #[derive(Default)]
 pub struct List {
@@ -29,23 +38,429 @@ pub struct List {
     input: Input,
     assignment_type: client_core::AssignmentType,
     assignments: Vec<Assignment>,
+    client: Option<PortalClient>,
+    query: AssignmentQuery,
+    /// The subject in `query` came from the command line as a name and is looked up on the
+    /// first load.
+    subject_unresolved: bool,
+    filter_input: Input,
+    /// The filter bar as last applied, restored on `Esc`.
+    applied_filter: String,
+    group_by_subject: bool,
+    sort: DateSort,
+    relative_dates: bool,
+    this_week: bool,
+    cache: Option<Cache>,
+    /// When the shown listing was fetched from the portal.
+    synced_at: Option<DateTime<Utc>>,
+    syncing: bool,
+    /// The listing refresh, aborted when superseded or abandoned.
+    sync_task: Option<AbortHandle>,
+    /// A details or subject lookup the user is waiting on.
+    loading: Option<Loading>,
+    /// Frame of the loading spinner, advanced every tick.
+    spinner: usize,
+    offline: bool,
+    sync_state: SyncState,
+    sync_state_path: PathBuf,
+    /// Assignments that came in since the last run.
+    new_ids: HashSet<String>,
 }
 
 impl List {
-    pub fn new() -> Self {
+    pub fn new(client: PortalClient, cache: Cache, query: AssignmentQuery) -> Self {
+        let sync_state_path = cache.sync_state_path("tui");
+        let sync_state = SyncState::load(&sync_state_path).unwrap_or_else(|err| {
+            warn!("Ignoring the saved sync state: {err}");
+            SyncState::default()
+        });
         Self {
+            sync_state,
+            sync_state_path,
             mode: Mode::ListScreen,
+            client: Some(client),
+            cache: Some(cache),
             list: AssignmentList::default(),
             state: State::Normal,
+            filter_input: Input::new(format_filter(&query)),
+            applied_filter: format_filter(&query),
+            subject_unresolved: query.subject.is_some(),
+            query,
             ..Default::default()
         }
     }
+
+    /// The filter bar applied to the current type.
+    fn current_query(&self) -> AssignmentQuery {
+        AssignmentQuery {
+            type_: self.assignment_type,
+            ..self.query.clone()
+        }
+    }
+
+    /// Shows the cached assignments of the current type right away and refreshes them from
+    /// the portal in the background. Filtered listings are not cached.
+    fn load(&mut self) -> Result<Option<Action>> {
+        let query = self.current_query();
+        let cache = self.cache.clone().unwrap();
+        self.assignments.clear();
+        self.synced_at = None;
+        if !query.is_filtered() {
+            match cache.listing(query.type_) {
+                Ok(Some(cached)) => {
+                    self.assignments = cached.value;
+                    self.synced_at = Some(cached.synced_at);
+                }
+                Ok(None) => {}
+                Err(err) => warn!("Ignoring the cached assignments: {err}"),
+            }
+        }
+        self.rebuild_list();
+
+        self.syncing = true;
+        let client = self.client.clone().unwrap();
+        let tx = self.command_tx.clone().unwrap();
+        let task = tokio::spawn(async move {
+            let action = match client.list(query.clone()).await {
+                Ok(assignments) => {
+                    if !query.is_filtered()
+                        && let Err(err) = cache.store_listing(query.type_, &assignments)
+                    {
+                        warn!("Unable to cache the assignments: {err}");
+                    }
+                    Action::AssignmentsLoaded(query, assignments)
+                }
+                Err(PortalError::SessionExpired) => Action::SessionExpired,
+                Err(err) => {
+                    if err.is_offline() {
+                        let _ = tx.send(Action::Offline);
+                    }
+                    Action::Notice {
+                        severity: Severity::Warning,
+                        message: format!("Unable to load assignments: {err}"),
+                        retry: Some(Box::new(Action::AssignmentType(query.type_))),
+                    }
+                }
+            };
+            let _ = tx.send(action);
+        });
+        if let Some(previous) = self.sync_task.replace(task.abort_handle()) {
+            previous.abort();
+        }
+        Ok(None)
+    }
+
+    /// Runs `request` in the background, showing `label` with a spinner until it finishes or
+    /// is abandoned with `Esc`. Replaces any request already in flight. If it fails, `retry`
+    /// is offered to run it again.
+    fn start_loading<F>(&mut self, label: String, retry: Option<Action>, request: F)
+    where
+        F: Future<Output = Result<Action, PortalError>> + Send + 'static,
+    {
+        let tx = self.command_tx.clone().unwrap();
+        let task = tokio::spawn({
+            let label = label.clone();
+            async move {
+                let action = match request.await {
+                    Ok(action) => action,
+                    Err(PortalError::SessionExpired) => Action::SessionExpired,
+                    Err(err) => Action::Notice {
+                        severity: Severity::Error,
+                        message: format!("{label}: {err}"),
+                        retry: retry.map(Box::new),
+                    },
+                };
+                let _ = tx.send(action);
+            }
+        });
+        if let Some(previous) = self.loading.replace(Loading {
+            label,
+            task: task.abort_handle(),
+        }) {
+            previous.task.abort();
+        }
+    }
+
+    /// Stops waiting for the current request, if there is one.
+    fn abandon_loading(&mut self) -> bool {
+        match self.loading.take() {
+            Some(loading) => {
+                loading.task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Shows the cached details of `assignment` and refreshes them in the background, or
+    /// fetches them first if none are cached.
+    fn open_assignment(&mut self, assignment: Assignment) -> Result<()> {
+        let cached = self
+            .cache
+            .as_ref()
+            .unwrap()
+            .details(&assignment.id)
+            .unwrap_or_else(|err| {
+                warn!("Ignoring the cached assignment details: {err}");
+                None
+            });
+        if let Some(cached) = cached {
+            self.refresh_details(assignment.clone());
+            return self.open_details(assignment, cached.value);
+        }
+        let client = self.client.clone().unwrap();
+        let label = format!("Loading “{}”", assignment.name);
+        let retry = Action::OpenAssignment(assignment.clone());
+        self.start_loading(label, Some(retry), async move {
+            let details = client.details(&assignment).await?;
+            Ok(Action::DetailsLoaded(assignment, details))
+        });
+        Ok(())
+    }
+
+    /// Shows the details screen for `assignment`.
+    fn open_details(&self, assignment: Assignment, details: AssignmentDetails) -> Result<()> {
+        let tx = self.command_tx.clone().unwrap();
+        tx.send(Action::ClearScreen)?;
+        tx.send(Action::Assignment(assignment))?;
+        tx.send(Action::AssignmentDetails(Some(details)))?;
+        tx.send(Action::Mode(Mode::CurrentAssignmentScreen))?;
+        Ok(())
+    }
+
+    /// Records a complete listing, remembering what is new since the last run. Nothing is
+    /// marked the first time a type is synced.
+    fn record_sync(&mut self, query: &AssignmentQuery, assignments: &[Assignment]) {
+        let first_sync = self.sync_state.synced_at(query.type_).is_none();
+        let diff = self.sync_state.record(query.type_, assignments);
+        if !first_sync {
+            self.new_ids
+                .extend(diff.new.into_iter().map(|assignment| assignment.id));
+        }
+        if let Err(err) = self.sync_state.save(&self.sync_state_path) {
+            warn!("Unable to save the sync state: {err}");
+        }
+    }
+
+    /// Fetches the details of `assignment` again and updates the cache and the Details view.
+    fn refresh_details(&self, assignment: Assignment) {
+        let client = self.client.clone().unwrap();
+        let cache = self.cache.clone().unwrap();
+        let tx = self.command_tx.clone().unwrap();
+        tokio::spawn(async move {
+            match client.details(&assignment).await {
+                Ok(details) => {
+                    if let Err(err) = cache.store_details(&details) {
+                        warn!("Unable to cache the assignment details: {err}");
+                    }
+                    let _ = tx.send(Action::AssignmentDetails(Some(details)));
+                }
+                Err(PortalError::SessionExpired) => {
+                    let _ = tx.send(Action::SessionExpired);
+                }
+                Err(err) => warn!("Showing cached details, refresh failed: {err}"),
+            }
+        });
+    }
+
+    /// The "offline / last synced" indicator.
+    fn sync_status(&self) -> Line<'static> {
+        let synced_at = self.synced_at.map(|synced_at| {
+            synced_at
+                .with_timezone(&Local)
+                .format("%d %b %H:%M")
+                .to_string()
+        });
+        let styles = &self.config.styles;
+        let muted = styles.style("list.muted");
+        let spinner = SPINNER[self.spinner % SPINNER.len()];
+        if let Some(loading) = &self.loading {
+            return Line::styled(
+                format!(" {spinner} {}… <Esc> to cancel ", loading.label),
+                styles.style("list.loading"),
+            );
+        }
+        match (self.syncing, self.offline, synced_at) {
+            (true, _, Some(synced_at)) => Line::styled(
+                format!(" {spinner} Syncing… last synced {synced_at} "),
+                muted,
+            ),
+            (true, _, None) => Line::styled(format!(" {spinner} Syncing… "), muted),
+            (false, true, synced_at) => Line::from(vec![
+                Span::styled(" Offline ", styles.style("list.offline")),
+                Span::styled(
+                    format!("last synced {} ", synced_at.as_deref().unwrap_or("never")),
+                    muted,
+                ),
+            ]),
+            (false, false, Some(synced_at)) => Line::styled(format!(" Synced {synced_at} "), muted),
+            (false, false, None) => Line::default(),
+        }
+    }
+
+    /// Rebuilds the list from the loaded assignments, keeping same-subject rows together when
+    /// grouping is on.
+    fn rebuild_list(&mut self) {
+        let today = Local::now().date_naive();
+        let mut assignments = self.assignments.clone();
+        if self.this_week {
+            assignments.retain(|assignment| {
+                assignment
+                    .issued
+                    .is_some_and(|issued| issued.iso_week() == today.iso_week())
+            });
+        }
+        match self.sort {
+            DateSort::Portal => {}
+            DateSort::OldestFirst => assignments.sort_by_key(|assignment| assignment.issued),
+            DateSort::NewestFirst => {
+                assignments.sort_by_key(|assignment| std::cmp::Reverse(assignment.issued))
+            }
+        }
+        if self.group_by_subject {
+            assignments.sort_by(|a, b| a.subject.cmp(&b.subject));
+        }
+        let selected = self.list.state.selected();
+        let selected_id = selected
+            .and_then(|index| self.list.shown().get(index))
+            .map(|item| item.assignment.id.clone());
+        self.list = assignments
+            .into_iter()
+            .map(|assignment| {
+                let date = match assignment.issued {
+                    Some(issued) if self.relative_dates => relative_date(issued, today),
+                    _ => assignment.date.clone(),
+                };
+                let is_new = self.new_ids.contains(&assignment.id);
+                AssignmentListItem::new(assignment, date, is_new)
+            })
+            .collect::<AssignmentList>();
+        self.apply_search();
+        let shown = self.list.shown();
+        let selected = selected_id
+            .and_then(|id| shown.iter().position(|item| item.assignment.id == id))
+            .or(selected)
+            .filter(|_| !shown.is_empty())
+            .map(|index| index.min(shown.len() - 1));
+        self.list.state.select(selected);
+    }
+
+    /// Narrows the list down to the items matching the search input.
+    fn apply_search(&mut self) {
+        let query = self.input.value();
+        if query.is_empty() {
+            self.list.filtered_items.clear();
+            return;
+        }
+        let mut matcher = Matcher::new(NucleoConfig::DEFAULT);
+        self.list.filtered_items = Pattern::new(
+            query,
+            CaseMatching::Ignore,
+            Normalization::Smart,
+            AtomKind::Substring,
+        )
+        .match_list(self.list.list_items.clone(), &mut matcher)
+        .into_iter()
+        .map(|(item, _)| item)
+        .collect();
+    }
+
+    /// Describes the active sort and quick filters for the list title.
+    fn view_summary(&self) -> String {
+        let sort = match self.sort {
+            DateSort::Portal => "portal order",
+            DateSort::NewestFirst => "newest first",
+            DateSort::OldestFirst => "oldest first",
+        };
+        let week = if self.this_week { ", this week" } else { "" };
+        let hints = self.config.keybindings.hints(
+            Mode::ListScreen,
+            &[
+                (Action::CycleSort, "sort"),
+                (Action::ToggleThisWeek, "this week"),
+                (Action::ToggleRelativeDates, "relative dates"),
+            ],
+        );
+        if hints.is_empty() {
+            format!("{sort}{week}")
+        } else {
+            format!("{sort}{week} ({hints})")
+        }
+    }
+
+    /// Applies the filter bar, resolving subject names to their portal IDs.
+    fn apply_filter(&mut self) -> Result<Option<Action>> {
+        let query = match parse_filter(self.filter_input.value()) {
+            Ok(query) => query,
+            Err(err) => return Ok(Some(Action::error(err))),
+        };
+        let filter = self.filter_input.value().to_string();
+        self.state = State::Normal;
+        if query.subject.is_none() {
+            return self.set_filter(filter, query);
+        }
+        self.resolve_filter(filter, query, None);
+        Ok(None)
+    }
+
+    /// Looks up the subject of `query` in the background and applies the filter once it is
+    /// found.
+    fn resolve_filter(
+        &mut self,
+        filter: String,
+        mut query: AssignmentQuery,
+        retry: Option<Action>,
+    ) {
+        let Some(subject) = query.subject.clone() else {
+            return;
+        };
+        let client = self.client.clone().unwrap();
+        let label = format!("Looking up subject `{subject}`");
+        self.start_loading(label, retry, async move {
+            query.subject = Some(client.resolve_subject(&subject).await?.id);
+            Ok(Action::FilterResolved { filter, query })
+        });
+    }
+
+    fn set_filter(&mut self, filter: String, query: AssignmentQuery) -> Result<Option<Action>> {
+        self.filter_input = Input::new(filter.clone());
+        self.applied_filter = filter;
+        self.query = query;
+        self.subject_unresolved = false;
+        self.load()
+    }
+}
+struct Loading {
+    label: String,
+    task: AbortHandle,
+}
+
+/// Order of the list by issue date.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum DateSort {
+    /// As listed by the portal.
+    #[default]
+    Portal,
+    NewestFirst,
+    OldestFirst,
+}
+
+impl DateSort {
+    fn next(self) -> Self {
+        match self {
+            DateSort::Portal => DateSort::NewestFirst,
+            DateSort::NewestFirst => DateSort::OldestFirst,
+            DateSort::OldestFirst => DateSort::Portal,
+        }
+    }
 }
+
 #[derive(Debug, Clone, Default, PartialEq)]
 enum State {
     #[default]
     Normal,
     Search,
+    Filter,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -58,6 +473,10 @@ pub struct AssignmentList {
 #[derive(Debug, Clone, Default)]
 pub struct AssignmentListItem {
     display: String,
+    /// Came in since the last run.
+    is_new: bool,
+    /// The date column, either as printed by the portal or relative to today.
+    date: String,
     assignment: Assignment,
 }
 
@@ -74,8 +493,25 @@ impl Component for List {
 
     fn update(&mut self, action: Action) -> Result<Option<Action>> {
         match action {
-            Action::Tick => {
-                // add any logic here that should run on every tick
+            Action::Tick if self.syncing || self.loading.is_some() => {
+                self.spinner = self.spinner.wrapping_add(1);
+                // A request that ended without its result action failed, and said so with a
+                // notice.
+                if self
+                    .loading
+                    .as_ref()
+                    .is_some_and(|loading| loading.task.is_finished())
+                {
+                    self.loading = None;
+                }
+                if self
+                    .sync_task
+                    .as_ref()
+                    .is_some_and(AbortHandle::is_finished)
+                {
+                    self.syncing = false;
+                    self.sync_task = None;
+                }
             }
             Action::Render => {
                 // add any logic here that should run on every render
@@ -83,20 +519,89 @@ impl Component for List {
             Action::Mode(mode) => {
                 self.enabled = mode == self.mode;
             }
+            Action::DetailsLoaded(assignment, details) => {
+                self.loading = None;
+                if let Err(err) = self.cache.as_ref().unwrap().store_details(&details) {
+                    warn!("Unable to cache the assignment details: {err}");
+                }
+                self.open_details(assignment, details)?;
+            }
+            Action::FilterResolved { filter, query } => {
+                self.loading = None;
+                return self.set_filter(filter, query);
+            }
+            Action::OpenAssignment(assignment) => self.open_assignment(assignment)?,
+            Action::SessionExpired => self.loading = None,
 
+            Action::AssignmentType(type_) if self.subject_unresolved => {
+                self.assignment_type = type_;
+                let retry = Action::AssignmentType(type_);
+                self.resolve_filter(
+                    self.applied_filter.clone(),
+                    self.current_query(),
+                    Some(retry),
+                );
+            }
             Action::AssignmentType(type_) => {
                 self.assignment_type = type_;
-                let assignments = match type_ {
-                    client_core::AssignmentType::Circular => block_on(get_circular()).unwrap(),
-                    client_core::AssignmentType::Homework => block_on(homework::get_hw()).unwrap(),
+                return self.load();
+            }
+            Action::AssignmentsLoaded(query, assignments) if query == self.current_query() => {
+                if !query.is_filtered() {
+                    self.record_sync(&query, &assignments);
+                }
+                self.assignments = assignments;
+                self.synced_at = Some(Utc::now());
+                self.syncing = false;
+                self.sync_task = None;
+                self.offline = false;
+                self.rebuild_list();
+            }
+            Action::Offline => self.offline = true,
+            _ if !self.enabled || self.state != State::Normal => {}
+            Action::SelectNone => self.list.select_none(),
+            Action::SelectNext => self.list.select_next(),
+            Action::SelectPrevious => self.list.select_previous(),
+            Action::SelectFirst => self.list.select_first(),
+            Action::SelectLast => self.list.select_last(),
+            Action::Back if self.abandon_loading() => {}
+            // With nothing to show yet, the first `Back` gives up on the listing.
+            Action::Back if self.syncing && self.assignments.is_empty() => {
+                if let Some(task) = self.sync_task.take() {
+                    task.abort();
+                }
+                self.syncing = false;
+            }
+            Action::Back => {
+                self.command_tx.clone().unwrap().send(Action::ClearScreen)?;
+                return Ok(Some(Action::Mode(Mode::Home)));
+            }
+            Action::OpenDetails => {
+                let Some(selected_index) = self.list.state.selected() else {
+                    return Ok(None);
+                };
+                let Some(selected_item) = self.list.shown().get(selected_index) else {
+                    return Ok(None);
                 };
-                self.assignments = assignments.clone();
-                let assignment_list_items = assignments
-                    .into_iter()
-                    .map(AssignmentListItem::new)
-                    .collect::<Vec<AssignmentListItem>>();
-                let assignment_list = AssignmentList::from_iter(assignment_list_items);
-                self.list = assignment_list;
+                self.open_assignment(selected_item.assignment.clone())?;
+            }
+            Action::Search => self.toggle_state(),
+            Action::EditFilter => self.state = State::Filter,
+            Action::GroupBySubject => {
+                self.group_by_subject = !self.group_by_subject;
+                self.rebuild_list();
+            }
+            Action::CycleSort => {
+                self.sort = self.sort.next();
+                self.rebuild_list();
+            }
+            Action::ToggleRelativeDates => {
+                self.relative_dates = !self.relative_dates;
+                self.rebuild_list();
+            }
+            Action::ToggleThisWeek => {
+                self.this_week = !self.this_week;
+                self.rebuild_list();
             }
             _ => {}
         }
@@ -113,61 +618,26 @@ impl Component for List {
                 KeyCode::Enter => {}
                 _ => {
                     self.input.handle_event(&crossterm::event::Event::Key(key));
-                    let val = self.input.value();
-                    let current_items = self.list.list_items.clone();
-                    let mut matcher = Matcher::new(NucleoConfig::DEFAULT);
-                    let matches = Pattern::new(
-                        val,
-                        CaseMatching::Ignore,
-                        Normalization::Smart,
-                        AtomKind::Substring,
-                    )
-                    .match_list(current_items, &mut matcher);
-
-                    let new_items = matches.into_iter().map(|item| item.0).collect_vec();
-                    self.list.filtered_items = new_items;
+                    self.apply_search();
                     self.list.state.select_first();
                 }
             }
             return Ok(None);
         }
-        match key.code {
-            KeyCode::Char('h') | KeyCode::Left => self.list.select_none(),
-            KeyCode::Char('j') | KeyCode::Down => self.list.select_next(),
-            KeyCode::Char('k') | KeyCode::Up => self.list.select_previous(),
-            KeyCode::Char('g') | KeyCode::Home => self.list.select_first(),
-            KeyCode::Char('G') | KeyCode::End => self.list.select_last(),
-            KeyCode::Esc => {
-                self.command_tx.clone().unwrap().send(Action::ClearScreen)?;
-                return Ok(Some(Action::Mode(Mode::Home)));
-            }
-            KeyCode::Enter => {
-                let selected_index = self.list.state.selected().unwrap();
-                let selected_assignment = if self.list.filtered_items.is_empty() {
-                    self.list.list_items[selected_index].assignment.clone()
-                } else {
-                    self.list.filtered_items[selected_index].assignment.clone()
-                };
-                let details = block_on(selected_assignment.get_details(self.assignment_type))
-                    .expect("Unable to get assignment details");
-                self.command_tx.clone().unwrap().send(Action::ClearScreen)?;
-                self.command_tx
-                    .clone()
-                    .unwrap()
-                    .send(Action::Assignment(selected_assignment))?;
-                self.command_tx
-                    .clone()
-                    .unwrap()
-                    .send(Action::AssignmentDetails(Some(details)))?;
-                self.command_tx
-                    .clone()
-                    .unwrap()
-                    .send(Action::Mode(Mode::CurrentAssignmentScreen))?;
+        if self.state == State::Filter {
+            match key.code {
+                KeyCode::Esc => {
+                    self.filter_input = Input::new(self.applied_filter.clone());
+                    self.state = State::Normal;
+                }
+                KeyCode::Enter => return self.apply_filter(),
+                _ => {
+                    self.filter_input
+                        .handle_event(&crossterm::event::Event::Key(key));
+                }
             }
-            KeyCode::Char('q') => return Ok(Some(Action::Quit)),
-            KeyCode::Char('/') => self.toggle_state(),
-            _ => {}
-        };
+            return Ok(None);
+        }
         Ok(None)
     }
 
@@ -182,38 +652,52 @@ impl Component for List {
         //           .collect()
         //   };
         let mut tw = TabWriter::new(vec![]);
-        if self.list.filtered_items.is_empty() {
-            write!(
-                tw,
-                "{}",
-                self.list
-                    .list_items
-                    .iter()
-                    .map(AssignmentListItem::format)
-                    .join("\n")
-            )
-            .unwrap();
+        let shown = if self.list.filtered_items.is_empty() {
+            &self.list.list_items
         } else {
-            write!(
-                tw,
-                "{}",
-                self.list
-                    .filtered_items
-                    .iter()
-                    .map(AssignmentListItem::format)
-                    .join("\n")
-            )
-            .unwrap();
-        }
+            &self.list.filtered_items
+        };
+        write!(
+            tw,
+            "{}",
+            shown.iter().map(AssignmentListItem::format).join("\n")
+        )
+        .unwrap();
         let written = String::from_utf8(tw.into_inner().unwrap()).unwrap();
-        let items = written.lines().map(|line| ListItem::new(line.to_string()));
+        let styles = &self.config.styles;
+        let items = written.lines().zip(shown).map(|(line, item)| {
+            let style = subject_style(&item.assignment.subject, styles);
+            let style = if item.is_new {
+                style.patch(styles.style("list.new"))
+            } else {
+                style
+            };
+            ListItem::new(line.to_string()).style(style)
+        });
 
         let list_style = match self.state {
-            State::Normal => Color::Yellow.into(),
-            State::Search => Style::default(),
+            State::Normal => styles.style("list.border"),
+            State::Search | State::Filter => Style::default(),
         };
+        let keys = &self.config.keybindings;
+        let hints = keys.hints(
+            self.mode,
+            &[
+                (Action::SelectNext, "move down"),
+                (Action::OpenDetails, "select"),
+                (Action::GroupBySubject, "group by subject"),
+            ],
+        );
+        let more_hints = keys.hints(
+            self.mode,
+            &[
+                (Action::Quit, "quit"),
+                (Action::Back, "go back"),
+                (Action::Help, "show help"),
+            ],
+        );
         let list = ListWidget::new(items)
-            .highlight_style(SELECTED_STYLE)
+            .highlight_style(styles.style("list.selected"))
             .highlight_symbol("> ")
             .highlight_spacing(HighlightSpacing::Always)
             .block(
@@ -223,21 +707,27 @@ impl Component for List {
                     .border_type(BorderType::Rounded)
                     .border_style(list_style)
                     .title_top(Line::raw("Assignments").centered().bold())
-                    .title_bottom(
-                        Line::raw("Press j/k or Up/Down to move, <Enter> to select").centered(),
-                    )
-                    .title_bottom(Line::raw("Press `q` to quit, <Esc> to go back").right_aligned()),
+                    .title_bottom(self.sync_status().left_aligned())
+                    .title_top(Line::raw(self.view_summary()).right_aligned())
+                    .title_bottom(Line::raw(format!("Press {hints}")).centered())
+                    .title_bottom(Line::raw(format!("Press {more_hints}")).right_aligned()),
             );
         let [top, center] =
             Layout::vertical([Constraint::Min(3), Constraint::Percentage(100)]).areas(area);
 
+        let [search_area, filter_area] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(top);
+
         let style = match self.state {
-            State::Normal => Style::default(),
-            State::Search => Color::Yellow.into(),
+            State::Search => styles.style("list.border.search"),
+            State::Normal | State::Filter => Style::default(),
         };
         let input_title = match self.state {
-            State::Normal => "Input (Press `/` to search)".to_string(),
             State::Search => "Input (Press `Esc`/`Tab` to exit)".to_string(),
+            State::Normal | State::Filter => match keys.key_for(self.mode, &Action::Search) {
+                Some(key) => format!("Input (Press `{key}` to search)"),
+                None => "Input".to_string(),
+            },
         };
 
         let input = Paragraph::new(self.input.value()).style(style).block(
@@ -245,13 +735,64 @@ impl Component for List {
                 .title(input_title)
                 .border_type(BorderType::Rounded),
         );
-        frame.render_widget(input, top);
+        frame.render_widget(input, search_area);
+
+        let filter_style = match self.state {
+            State::Filter => styles.style("list.border.filter"),
+            State::Normal | State::Search => Style::default(),
+        };
+        let filter_title = match self.state {
+            State::Filter => {
+                "Filter (from:YYYY-MM-DD to:YYYY-MM-DD subject:NAME, <Enter> to apply)".to_string()
+            }
+            State::Normal | State::Search => match keys.key_for(self.mode, &Action::EditFilter) {
+                Some(key) => format!("Filter (Press `{key}` to edit)"),
+                None => "Filter".to_string(),
+            },
+        };
+        let filter = Paragraph::new(self.filter_input.value())
+            .style(filter_style)
+            .block(
+                Block::bordered()
+                    .title(filter_title)
+                    .border_type(BorderType::Rounded),
+            );
+        frame.render_widget(filter, filter_area);
         frame.render_stateful_widget(list, center, &mut self.list.state);
+        if shown.is_empty() && self.syncing {
+            let spinner = SPINNER[self.spinner % SPINNER.len()];
+            let placeholder = Paragraph::new(format!("{spinner} Loading…"))
+                .style(styles.style("list.muted"))
+                .centered();
+            let [middle] = Layout::vertical([Constraint::Length(1)])
+                .flex(layout::Flex::Center)
+                .areas(center);
+            frame.render_widget(placeholder, middle);
+        }
         Ok(())
     }
     fn get_mode(&self) -> crate::app::Mode {
         self.mode
     }
+
+    fn takes_text_input(&self) -> bool {
+        self.enabled && self.state != State::Normal
+    }
+
+    fn key_help(&self) -> Option<HelpSection> {
+        let section = HelpSection::new("Assignments");
+        let section = match self.state {
+            State::Search => section
+                .key("type", "Fuzzy search the list")
+                .key("esc, tab", "Leave search"),
+            State::Filter => section
+                .key("type", "Edit from:YYYY-MM-DD to:YYYY-MM-DD subject:NAME")
+                .key("enter", "Apply the filter")
+                .key("esc", "Discard changes"),
+            State::Normal => return None,
+        };
+        Some(section)
+    }
 }
 
 impl AssignmentList {
@@ -274,6 +815,17 @@ impl AssignmentList {
         self.state.select_last();
     }
 }
+impl AssignmentList {
+    /// The items on screen: the search results, or every item if there are none.
+    fn shown(&self) -> &[AssignmentListItem] {
+        if self.filtered_items.is_empty() {
+            &self.list_items
+        } else {
+            &self.filtered_items
+        }
+    }
+}
+
 impl FromIterator<AssignmentListItem> for AssignmentList {
     fn from_iter<I: IntoIterator<Item = AssignmentListItem>>(iter: I) -> Self {
         let items = iter.into_iter().collect();
@@ -290,30 +842,94 @@ impl List {
     pub fn toggle_state(&mut self) {
         self.state = match self.state {
             State::Normal => State::Search,
-            State::Search => State::Normal,
+            State::Search | State::Filter => State::Normal,
         };
     }
 }
 
+/// Parses the filter bar, e.g. `from:2025-04-01 to:2025-04-30 subject:12`.
+fn parse_filter(input: &str) -> Result<AssignmentQuery, String> {
+    let mut query = AssignmentQuery::default();
+    for token in input.split_whitespace() {
+        let Some((key, value)) = token.split_once(':') else {
+            return Err(format!("Expected `key:value` in the filter, got `{token}`"));
+        };
+        let parse_date = |value: &str| {
+            value
+                .parse::<NaiveDate>()
+                .map_err(|_| format!("Invalid date `{value}`, expected YYYY-MM-DD"))
+        };
+        match key {
+            "from" => query.from = Some(parse_date(value)?),
+            "to" => query.to = Some(parse_date(value)?),
+            "subject" => query.subject = Some(value.to_string()),
+            _ => return Err(format!("Unknown filter `{key}`")),
+        }
+    }
+    Ok(query)
+}
+
+/// Picks a stable `list.subject.N` style per subject so homework of the same subject is easy
+/// to spot.
+fn subject_style(subject: &str, styles: &Styles) -> Style {
+    if subject.is_empty() {
+        return Style::default();
+    }
+    let index = subject.bytes().map(usize::from).sum::<usize>() % SUBJECT_STYLES;
+    styles.style(&format!("list.subject.{index}"))
+}
+
+/// Describes `date` relative to `today`, e.g. "3 days ago".
+fn relative_date(date: NaiveDate, today: NaiveDate) -> String {
+    let days = (today - date).num_days();
+    match days {
+        0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        -1 => "tomorrow".to_string(),
+        2..14 => format!("{days} days ago"),
+        14..60 => format!("{} weeks ago", days / 7),
+        60.. => format!("{} months ago", days / 30),
+        _ => format!("in {} days", -days),
+    }
+}
+
+fn format_filter(query: &AssignmentQuery) -> String {
+    let mut parts = vec![];
+    if let Some(from) = query.from {
+        parts.push(format!("from:{from}"));
+    }
+    if let Some(to) = query.to {
+        parts.push(format!("to:{to}"));
+    }
+    if let Some(subject) = &query.subject {
+        parts.push(format!("subject:{subject}"));
+    }
+    parts.join(" ")
+}
+
 impl AssignmentListItem {
     fn format(&self) -> String {
         format!(
-            "{}\t{}\t{}\t{}\t{}",
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            if self.is_new { "NEW" } else { "" },
             self.assignment.s_no,
             self.assignment.id,
-            self.assignment.date,
+            self.date,
             self.assignment.type_,
+            self.assignment.subject,
             self.assignment.name
         )
     }
-    fn new(assignment: Assignment) -> Self {
+    fn new(assignment: Assignment, date: String, is_new: bool) -> Self {
         let display = format!(
-            "{} {} {}",
-            assignment.name, assignment.type_, assignment.date
+            "{} {} {} {}",
+            assignment.name, assignment.subject, assignment.type_, date
         );
         Self {
             assignment,
             display,
+            date,
+            is_new,
         }
     }
 }
@@ -330,3 +946,100 @@ impl AsRef<str> for AssignmentListItem {
         self.display.as_ref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_filter() {
+        let query = parse_filter("from:2025-04-01 subject:12").unwrap();
+        assert_eq!(query.from, NaiveDate::from_ymd_opt(2025, 4, 1));
+        assert_eq!(query.to, None);
+        assert_eq!(query.subject.as_deref(), Some("12"));
+        assert_eq!(format_filter(&query), "from:2025-04-01 subject:12");
+    }
+
+    #[test]
+    fn test_relative_date() {
+        let today = NaiveDate::from_ymd_opt(2025, 4, 14).unwrap();
+        let days_ago = |days| relative_date(today - chrono::Days::new(days), today);
+        assert_eq!(days_ago(0), "today");
+        assert_eq!(days_ago(1), "yesterday");
+        assert_eq!(days_ago(3), "3 days ago");
+        assert_eq!(days_ago(21), "3 weeks ago");
+        assert_eq!(days_ago(90), "3 months ago");
+        assert_eq!(
+            relative_date(today + chrono::Days::new(2), today),
+            "in 2 days"
+        );
+    }
+
+    #[test]
+    fn test_selection_stays_in_range() {
+        let assignment = |id: &str| Assignment {
+            id: id.to_string(),
+            ..Default::default()
+        };
+        let mut list = List {
+            enabled: true,
+            assignments: vec![assignment("1"), assignment("2"), assignment("3")],
+            ..Default::default()
+        };
+        list.rebuild_list();
+        list.list.state.select(Some(2));
+        list.assignments.truncate(1);
+        list.rebuild_list();
+        assert_eq!(list.list.state.selected(), Some(0));
+
+        list.assignments.clear();
+        list.rebuild_list();
+        assert_eq!(list.list.state.selected(), None);
+        list.list.state.select(Some(1));
+        assert!(list.update(Action::OpenDetails).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rebuild_keeps_the_search() {
+        let assignment = |id: &str, name: &str| Assignment {
+            id: id.to_string(),
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let mut list = List {
+            enabled: true,
+            input: Input::new("bot".to_string()),
+            assignments: vec![
+                assignment("1", "Botany"),
+                assignment("2", "Chemistry"),
+                assignment("3", "Robotics"),
+            ],
+            ..Default::default()
+        };
+        list.rebuild_list();
+        list.list.state.select(Some(1));
+        assert_eq!(list.list.shown()[1].assignment.id, "3");
+
+        // A sync brings in a new assignment at the top.
+        list.assignments
+            .insert(0, assignment("4", "Bottle rockets"));
+        list.rebuild_list();
+        let shown = list
+            .list
+            .shown()
+            .iter()
+            .map(|item| item.assignment.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(shown, ["4", "1", "3"]);
+        assert_eq!(list.list.state.selected(), Some(2));
+    }
+
+    #[test]
+    fn test_parse_filter_invalid() {
+        assert!(parse_filter("from:01/04/2025").is_err());
+        assert!(parse_filter("teacher:smith").is_err());
+        assert!(parse_filter("2025-04-01").is_err());
+    }
+}
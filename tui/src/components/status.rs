@@ -0,0 +1,316 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use client_core::DownloadEvent;
+use color_eyre::Result;
+use crossterm::event::KeyEvent;
+use layout::Flex;
+use ratatui::{prelude::*, widgets::*};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::Component;
+use crate::{
+    action::{Action, Severity},
+    app::Mode,
+    config::Config,
+};
+
+/// Notices kept in the history pane.
+const HISTORY_LEN: usize = 200;
+
+impl Severity {
+    /// How long a toast stays up.
+    fn timeout(self) -> Duration {
+        match self {
+            Severity::Info => Duration::from_secs(3),
+            Severity::Warning => Duration::from_secs(6),
+            Severity::Error => Duration::from_secs(10),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+
+    /// Key of the severity's style.
+    fn style_key(self) -> &'static str {
+        match self {
+            Severity::Info => "status.info",
+            Severity::Warning => "status.warning",
+            Severity::Error => "status.error",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Notice {
+    severity: Severity,
+    message: String,
+    at: DateTime<Local>,
+    /// Runs the failed operation again.
+    retry: Option<Action>,
+}
+
+/// Toasts for errors and other notices in every mode, with a history pane
+/// ([`Action::ToggleMessages`]) and a key to retry the last failed operation
+/// ([`Action::RetryFailed`]).
+#[derive(Default)]
+pub struct Status {
+    command_tx: Option<UnboundedSender<Action>>,
+    config: Config,
+    /// Oldest first.
+    history: Vec<Notice>,
+    /// When the last notice was toasted, until it is dismissed.
+    toast_since: Option<Instant>,
+    show_history: bool,
+    scroll: usize,
+    /// Where keys are looked up, for the hints.
+    mode: Mode,
+    popup_visible: bool,
+}
+
+impl Status {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(&mut self, severity: Severity, message: String, retry: Option<Action>) {
+        if self.history.len() == HISTORY_LEN {
+            self.history.remove(0);
+        }
+        self.history.push(Notice {
+            severity,
+            message,
+            at: Local::now(),
+            retry,
+        });
+        self.toast_since = Some(Instant::now());
+    }
+
+    /// The latest failure that can still be retried.
+    fn retryable(&mut self) -> Option<&mut Notice> {
+        self.history
+            .iter_mut()
+            .rev()
+            .find(|notice| notice.retry.is_some())
+    }
+
+    fn draw_toast(&self, frame: &mut Frame, area: Rect) {
+        let (Some(notice), Some(_)) = (self.history.last(), self.toast_since) else {
+            return;
+        };
+        let mode = if self.mode == Mode::CurrentAssignmentScreen && self.popup_visible {
+            Mode::Attachments
+        } else {
+            self.mode
+        };
+        let mut actions = vec![(Action::ToggleMessages, "history")];
+        if notice.retry.is_some() {
+            actions.insert(0, (Action::RetryFailed, "retry"));
+        }
+        let hint = actions
+            .into_iter()
+            .filter_map(|(action, what)| {
+                let key = self.config.keybindings.key_for(mode, &action)?;
+                Some(format!("{key} {what}"))
+            })
+            .collect::<Vec<_>>();
+        let width = area.width.min(60);
+        let text = Paragraph::new(notice.message.clone())
+            .wrap(Wrap { trim: true })
+            .block(
+                Block::bordered()
+                    .border_type(BorderType::Rounded)
+                    .border_style(self.config.styles.style(notice.severity.style_key()))
+                    .title_top(Line::raw(format!(" {} ", notice.severity.label())).bold())
+                    .title_bottom(Line::raw(format!(" {} ", hint.join(", "))).right_aligned()),
+            );
+        let lines = notice
+            .message
+            .chars()
+            .count()
+            .div_ceil(width.saturating_sub(2).max(1) as usize)
+            .max(1);
+        let height = (lines as u16 + 2).min(area.height / 3).max(3);
+        let toast = Rect {
+            x: area.right().saturating_sub(width + 1),
+            y: area.bottom().saturating_sub(height + 1),
+            width,
+            height,
+        };
+        frame.render_widget(Clear, toast);
+        frame.render_widget(text, toast);
+    }
+
+    fn draw_history(&mut self, frame: &mut Frame, area: Rect) {
+        let [pane] = Layout::horizontal([Constraint::Percentage(70)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [pane] = Layout::vertical([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(pane);
+        let styles = &self.config.styles;
+        let lines = self
+            .history
+            .iter()
+            .rev()
+            .map(|notice| {
+                Line::from(vec![
+                    Span::styled(
+                        notice.at.format("%H:%M:%S ").to_string(),
+                        styles.style("status.time"),
+                    ),
+                    Span::styled(
+                        format!("{:<8}", notice.severity.label()),
+                        styles.style(notice.severity.style_key()),
+                    ),
+                    Span::raw(notice.message.clone()),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let visible = pane.height.saturating_sub(2) as usize;
+        self.scroll = self.scroll.min(lines.len().saturating_sub(visible));
+        let hints = self.config.keybindings.hints(
+            Mode::Overlay,
+            &[
+                (Action::ScrollDown, "scroll"),
+                (Action::RetryFailed, "retry the last failure"),
+                (Action::Back, "close"),
+            ],
+        );
+        let history = Paragraph::new(lines).scroll((self.scroll as u16, 0)).block(
+            Block::bordered()
+                .border_type(BorderType::Rounded)
+                .title_top(Line::raw("Messages").centered().bold())
+                .title_bottom(Line::raw(hints).centered()),
+        );
+        frame.render_widget(Clear, pane);
+        frame.render_widget(history, pane);
+    }
+}
+
+impl Component for Status {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.config = config;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action) -> Result<Option<Action>> {
+        match action {
+            Action::Tick => {
+                let timeout = self.history.last().map(|notice| notice.severity.timeout());
+                if let (Some(since), Some(timeout)) = (self.toast_since, timeout)
+                    && since.elapsed() >= timeout
+                {
+                    self.toast_since = None;
+                }
+            }
+            Action::Mode(mode) => self.mode = mode,
+            Action::ToggleDownloadPopup => self.popup_visible = !self.popup_visible,
+            Action::Notice {
+                severity,
+                message,
+                retry,
+            } => self.push(severity, message, retry.map(|retry| *retry)),
+            Action::Download(DownloadEvent::Finished { path, .. }) => {
+                self.push(Severity::Info, format!("Saved {}", path.display()), None)
+            }
+            Action::ToggleMessages => {
+                self.show_history = !self.show_history;
+                self.scroll = 0;
+                self.toast_since = None;
+            }
+            Action::RetryFailed => {
+                let Some(notice) = self.retryable() else {
+                    return Ok(None);
+                };
+                let retry = notice.retry.take();
+                self.toast_since = None;
+                return Ok(retry);
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        if !self.show_history {
+            return Ok(None);
+        }
+        match self.config.keybindings.action(Mode::Overlay, key) {
+            Some(Action::Back | Action::ToggleMessages) => self.show_history = false,
+            Some(Action::RetryFailed) => return self.update(Action::RetryFailed),
+            Some(Action::ScrollDown) => self.scroll = self.scroll.saturating_add(1),
+            Some(Action::ScrollUp) => self.scroll = self.scroll.saturating_sub(1),
+            Some(Action::SelectFirst) => self.scroll = 0,
+            Some(Action::SelectLast) => self.scroll = usize::MAX,
+            Some(Action::Quit) => return Ok(Some(Action::Quit)),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
+        if self.show_history {
+            self.draw_history(frame, area);
+        } else {
+            self.draw_toast(frame, area);
+        }
+        Ok(())
+    }
+
+    fn get_mode(&self) -> Mode {
+        Mode::Home
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+
+    fn captures_keys(&self) -> bool {
+        self.show_history
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyCode;
+
+    use super::*;
+
+    #[test]
+    fn test_history_keys_follow_the_bindings() -> Result<()> {
+        let mut config = Config::new()?;
+        config.keybindings.get_mut(&Mode::Overlay).unwrap().insert(
+            vec![KeyEvent::from(KeyCode::Char('x'))],
+            Action::RetryFailed,
+        );
+        let mut status = Status::new();
+        status.register_config_handler(config)?;
+        let retry = Action::OpenDetails;
+        status.update(Action::Notice {
+            severity: Severity::Error,
+            message: "Unable to load".to_string(),
+            retry: Some(Box::new(retry.clone())),
+        })?;
+        status.update(Action::ToggleMessages)?;
+        assert!(status.captures_keys());
+
+        assert_eq!(
+            status.handle_key_event(KeyEvent::from(KeyCode::Char('x')))?,
+            Some(retry)
+        );
+        status.handle_key_event(KeyEvent::from(KeyCode::Esc))?;
+        assert!(!status.captures_keys());
+        Ok(())
+    }
+}
@@ -1,12 +1,18 @@
 #![allow(dead_code)] // Remove this once you start using the code
 
-use std::{collections::HashMap, env, path::PathBuf};
-
-use crate::{action::Action, app::Mode};
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+};
+
+use crate::{action::Action, app::Mode, theme::ThemeName};
+use client_core::{DownloadConfig, PortalConfig};
 use color_eyre::Result;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use derive_deref::{Deref, DerefMut};
-use directories::ProjectDirs;
+use directories::{ProjectDirs, UserDirs};
+use itertools::Itertools;
 use lazy_static::lazy_static;
 use ratatui::style::{Color, Modifier, Style};
 use serde::{Deserialize, de::Deserializer};
@@ -28,15 +34,20 @@ pub struct Config {
     #[serde(default)]
     pub keybindings: KeyBindings,
     #[serde(default)]
+    pub theme: ThemeName,
+    /// Overrides of the theme's styles, by key.
+    #[serde(default)]
     pub styles: Styles,
+    #[serde(default)]
+    pub portal: PortalConfig,
+    #[serde(default)]
+    pub downloads: DownloadConfig,
+    #[serde(default)]
+    pub viewers: Viewers,
 }
 
 lazy_static! {
     pub static ref PROJECT_NAME: String = env!("CARGO_CRATE_NAME").to_uppercase().to_string();
-    pub static ref DATA_FOLDER: Option<PathBuf> =
-        env::var(format!("{}_DATA", PROJECT_NAME.clone()))
-            .ok()
-            .map(PathBuf::from);
     pub static ref CONFIG_FOLDER: Option<PathBuf> =
         env::var(format!("{}_CONFIG", PROJECT_NAME.clone()))
             .ok()
@@ -48,10 +59,24 @@ impl Config {
         let default_config: Config = json5::from_str(CONFIG).unwrap();
         let data_dir = get_data_dir();
         let config_dir = get_config_dir();
-        let builder = config::Config::builder()
+        let mut builder = config::Config::builder()
             .set_default("data_dir", data_dir.to_str().unwrap())?
             .set_default("config_dir", config_dir.to_str().unwrap())?;
 
+        let config_files = [
+            ("config.json5", config::FileFormat::Json5),
+            ("config.json", config::FileFormat::Json),
+            ("config.yaml", config::FileFormat::Yaml),
+            ("config.toml", config::FileFormat::Toml),
+            ("config.ini", config::FileFormat::Ini),
+        ];
+        for (file, format) in &config_files {
+            let source = config::File::from(config_dir.join(file))
+                .format(*format)
+                .required(false);
+            builder = builder.add_source(source);
+        }
+
         let mut cfg: Self = builder.build()?.try_deserialize()?;
 
         for (mode, default_bindings) in default_config.keybindings.iter() {
@@ -62,24 +87,40 @@ impl Config {
                     .or_insert_with(|| cmd.clone());
             }
         }
-        for (mode, default_styles) in default_config.styles.iter() {
-            let user_styles = cfg.styles.entry(*mode).or_default();
-            for (style_key, style) in default_styles.iter() {
-                user_styles.entry(style_key.clone()).or_insert(*style);
-            }
-        }
+        let mut styles = cfg.theme.styles();
+        styles.extend(cfg.styles.drain());
+        cfg.styles = Styles(styles);
 
         Ok(cfg)
     }
 }
 
+/// Shared with the `client_core` CLI, so both find the saved session.
 pub fn get_data_dir() -> PathBuf {
-    if let Some(s) = DATA_FOLDER.clone() {
-        s
-    } else if let Some(proj_dirs) = project_directory() {
-        proj_dirs.data_local_dir().to_path_buf()
-    } else {
-        PathBuf::from(".").join(".data")
+    client_core::session::data_dir()
+}
+
+pub fn get_session_path() -> PathBuf {
+    client_core::session::session_path()
+}
+
+pub fn get_cache_dir() -> PathBuf {
+    get_data_dir().join("cache")
+}
+
+/// Root folder for saved attachments: the configured one, or `entab` in the user's downloads.
+pub fn get_download_dir(config: &DownloadConfig) -> PathBuf {
+    let user_dirs = UserDirs::new();
+    match &config.dir {
+        Some(dir) => match (dir.strip_prefix("~"), &user_dirs) {
+            (Ok(rest), Some(user_dirs)) => user_dirs.home_dir().join(rest),
+            _ => dir.clone(),
+        },
+        None => user_dirs
+            .as_ref()
+            .and_then(|user_dirs| user_dirs.download_dir())
+            .map(|dir| dir.join("entab"))
+            .unwrap_or_else(|| PathBuf::from(".").join("downloads")),
     }
 }
 
@@ -122,6 +163,33 @@ impl<'de> Deserialize<'de> for KeyBindings {
     }
 }
 
+impl KeyBindings {
+    /// The action bound to the single key `key` in `mode`.
+    pub fn action(&self, mode: Mode, key: KeyEvent) -> Option<&Action> {
+        self.get(&mode)?.get(&vec![key])
+    }
+
+    /// The shortest key sequence bound to `action` in `mode`, for hints in the UI.
+    pub fn key_for(&self, mode: Mode, action: &Action) -> Option<String> {
+        self.get(&mode)?
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(keys, _)| keys.iter().map(key_event_to_string).join(" "))
+            .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+    }
+
+    /// "`key` to do something" for each bound action in `mode`, joined with commas.
+    pub fn hints(&self, mode: Mode, hints: &[(Action, &str)]) -> String {
+        hints
+            .iter()
+            .filter_map(|(action, what)| {
+                let key = self.key_for(mode, action)?;
+                Some(format!("`{key}` to {what}"))
+            })
+            .join(", ")
+    }
+}
+
 fn parse_key_event(raw: &str) -> Result<KeyEvent, String> {
     let raw_lower = raw.to_ascii_lowercase();
     let (remaining, modifiers) = extract_modifiers(&raw_lower);
@@ -292,25 +360,28 @@ pub fn parse_key_sequence(raw: &str) -> Result<Vec<KeyEvent>, String> {
     sequences.into_iter().map(parse_key_event).collect()
 }
 
+/// Styles by key, e.g. `list.selected`.
 #[derive(Clone, Debug, Default, Deref, DerefMut)]
-pub struct Styles(pub HashMap<Mode, HashMap<String, Style>>);
+pub struct Styles(pub HashMap<String, Style>);
+
+impl Styles {
+    /// The style for `key`, or the terminal's default if neither the theme nor the config
+    /// has one.
+    pub fn style(&self, key: &str) -> Style {
+        self.get(key).copied().unwrap_or_default()
+    }
+}
 
 impl<'de> Deserialize<'de> for Styles {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let parsed_map = HashMap::<Mode, HashMap<String, String>>::deserialize(deserializer)?;
+        let parsed_map = HashMap::<String, String>::deserialize(deserializer)?;
 
         let styles = parsed_map
             .into_iter()
-            .map(|(mode, inner_map)| {
-                let converted_inner_map = inner_map
-                    .into_iter()
-                    .map(|(str, style)| (str, parse_style(&style)))
-                    .collect();
-                (mode, converted_inner_map)
-            })
+            .map(|(key, style)| (key, parse_style(&style)))
             .collect();
 
         Ok(Styles(styles))
@@ -378,6 +449,12 @@ fn parse_color(s: &str) -> Option<Color> {
                 .parse::<u8>()
                 .unwrap_or_default();
         Some(Color::Indexed(c))
+    } else if let Some(hex) = s.strip_prefix('#') {
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        if hex.len() != 6 {
+            return None;
+        }
+        Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?))
     } else if s.contains("rgb") {
         let red = (s.as_bytes()[3] as char).to_digit(10).unwrap_or_default() as u8;
         let green = (s.as_bytes()[4] as char).to_digit(10).unwrap_or_default() as u8;
@@ -421,6 +498,65 @@ fn parse_color(s: &str) -> Option<Color> {
     }
 }
 
+/// Program used when no viewer is configured for a file.
+pub const FALLBACK_VIEWER: &str = if cfg!(target_os = "macos") {
+    "open"
+} else {
+    "xdg-open"
+};
+
+/// Commands that open attachments, keyed by extension (`pdf`), MIME type (`image/png`) or
+/// MIME group (`image/*`). A `{}` in the command is replaced by the file's path, otherwise the
+/// path is appended.
+#[derive(Clone, Debug, Default, Deref, DerefMut, Deserialize)]
+pub struct Viewers(pub HashMap<String, String>);
+
+impl Viewers {
+    /// The configured command for `file_name`, most specific match first.
+    fn viewer_for(&self, file_name: &str) -> Option<&str> {
+        let extension = Path::new(file_name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        let mime = mime_guess::from_path(file_name).first();
+        let keys = [
+            extension,
+            mime.as_ref().map(|mime| mime.essence_str().to_string()),
+            mime.as_ref().map(|mime| format!("{}/*", mime.type_())),
+            Some("*".to_string()),
+        ];
+        keys.into_iter()
+            .flatten()
+            .find_map(|key| self.get(&key))
+            .map(String::as_str)
+            .filter(|command| !command.trim().is_empty())
+    }
+
+    /// Program and arguments that open `path`.
+    pub fn command(&self, path: &Path) -> Vec<String> {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let command = self.viewer_for(&file_name).unwrap_or(FALLBACK_VIEWER);
+        let path = path.to_string_lossy();
+        let mut args = command
+            .split_whitespace()
+            .map(|arg| arg.replace("{}", &path))
+            .collect::<Vec<_>>();
+        if !command.contains("{}") {
+            args.push(path.to_string());
+        }
+        args
+    }
+
+    /// Name of the program that opens `file_name`, for key hints.
+    pub fn program(&self, file_name: &str) -> String {
+        let command = self.viewer_for(file_name).unwrap_or(FALLBACK_VIEWER);
+        let program = command.split_whitespace().next().unwrap_or(FALLBACK_VIEWER);
+        Path::new(program)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| program.to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use pretty_assertions::assert_eq;
@@ -468,6 +604,16 @@ mod tests {
         assert_eq!(color, Some(Color::Indexed(expected)));
     }
 
+    #[test]
+    fn test_parse_color_hex() {
+        assert_eq!(parse_color("#1e293b"), Some(Color::Rgb(0x1e, 0x29, 0x3b)));
+        assert_eq!(parse_color("#12345"), None);
+        let style = parse_style("bold #ffffff on #000000");
+        assert_eq!(style.fg, Some(Color::Rgb(255, 255, 255)));
+        assert_eq!(style.bg, Some(Color::Rgb(0, 0, 0)));
+        assert!(style.add_modifier.contains(Modifier::BOLD));
+    }
+
     #[test]
     fn test_parse_color_unknown() {
         let color = parse_color("unknown");
@@ -488,6 +634,59 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_config_applies_theme() -> Result<()> {
+        let c = Config::new()?;
+        assert_eq!(c.theme, ThemeName::Dark);
+        for (key, style) in ThemeName::Dark.styles() {
+            assert_eq!(c.styles.style(&key), style, "{key}");
+        }
+        assert_eq!(c.styles.style("no.such.key"), Style::default());
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_bindings_cover_every_mode() -> Result<()> {
+        use strum::IntoEnumIterator;
+
+        let c = Config::new()?;
+        for mode in Mode::iter() {
+            assert_eq!(
+                c.keybindings.key_for(mode, &Action::Help).as_deref(),
+                Some("?")
+            );
+        }
+        assert_eq!(
+            c.keybindings
+                .get(&Mode::ListScreen)
+                .unwrap()
+                .get(&parse_key_sequence("<shift-g>").unwrap()),
+            Some(&Action::SelectLast)
+        );
+        assert_eq!(
+            c.keybindings
+                .key_for(Mode::Attachments, &Action::ToggleSelect)
+                .as_deref(),
+            Some("space")
+        );
+        assert_eq!(
+            c.keybindings
+                .action(Mode::Overlay, KeyEvent::from(KeyCode::Esc)),
+            Some(&Action::Back)
+        );
+        assert_eq!(
+            c.keybindings.hints(
+                Mode::CurrentAssignmentScreen,
+                &[
+                    (Action::OpenAttachments, "download"),
+                    (Action::Search, "search")
+                ]
+            ),
+            "`d` to download"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_simple_keys() {
         assert_eq!(
@@ -569,4 +768,26 @@ mod tests {
             KeyEvent::new(KeyCode::Enter, KeyModifiers::ALT)
         );
     }
+
+    #[test]
+    fn test_viewers() {
+        let viewers = Viewers(HashMap::from([
+            ("pdf".to_string(), "zathura --fork".to_string()),
+            ("image/*".to_string(), "imv {} -f".to_string()),
+        ]));
+        assert_eq!(
+            viewers.command(Path::new("/tmp/Notes.PDF")),
+            ["zathura", "--fork", "/tmp/Notes.PDF"]
+        );
+        assert_eq!(
+            viewers.command(Path::new("/tmp/map.jpg")),
+            ["imv", "/tmp/map.jpg", "-f"]
+        );
+        assert_eq!(
+            viewers.command(Path::new("/tmp/fees.xlsx")),
+            [FALLBACK_VIEWER, "/tmp/fees.xlsx"]
+        );
+        assert_eq!(viewers.program("Notes.pdf"), "zathura");
+        assert_eq!(viewers.program("fees.xlsx"), FALLBACK_VIEWER);
+    }
 }
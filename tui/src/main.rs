@@ -1,10 +1,13 @@
 use clap::{CommandFactory, Parser};
 use cli::Cli;
-use client_core::login;
+use client_core::{Cache, PortalClient};
 use color_eyre::Result;
 use std::io;
 
-use crate::app::App;
+use crate::{
+    app::App,
+    config::{Config, get_cache_dir, get_session_path},
+};
 
 mod action;
 mod app;
@@ -13,6 +16,9 @@ mod components;
 mod config;
 mod errors;
 mod logging;
+mod session;
+mod sync;
+mod theme;
 mod tui;
 
 #[tokio::main]
@@ -20,24 +26,63 @@ async fn main() -> Result<()> {
     crate::errors::init()?;
     crate::logging::init()?;
 
-    let args = Cli::parse();
-    if let Some(command) = args.command {
+    let mut args = Cli::parse();
+    let mut config = Config::new()?;
+    args.apply_portal_overrides(&mut config.portal);
+
+    if let Some(command) = args.command.take() {
         match command {
             cli::Command::Completions { shell } => {
                 let mut cmd = Cli::command();
                 let name = cmd.get_name().to_string();
                 clap_complete::generate(shell, &mut cmd, name, &mut io::stdout());
-                return Ok(());
+            }
+            cli::Command::Session { command } => session::run(command, &config).await?,
+            cli::Command::Subjects => {
+                let session = session::load_or_login(
+                    &config,
+                    args.login,
+                    args.store_credentials,
+                    args.fetch_credentials,
+                )
+                .await?;
+                let client = PortalClient::new(config.portal.clone(), session)?;
+                for subject in client.subjects().await? {
+                    println!("{}\t{}", subject.id, subject.name);
+                }
+            }
+            cli::Command::Sync { type_, details } => {
+                let session = session::load_or_login(
+                    &config,
+                    args.login,
+                    args.store_credentials,
+                    args.fetch_credentials,
+                )
+                .await?;
+                let client = PortalClient::new(config.portal.clone(), session)?
+                    .with_session_file(get_session_path());
+                let cache = Cache::open(get_cache_dir(), client.session().student())?;
+                sync::run(&client, &cache, type_, details).await?;
             }
         }
+        return Ok(());
     }
 
-    if args.login {
-        login::Login::login(args.store_credentials, args.fetch_credentials)
-            .await
-            .expect("Failed to login");
-    }
-    let mut app = App::new(args.tick_rate, args.frame_rate)?;
+    let session = session::load_or_login(
+        &config,
+        args.login,
+        args.store_credentials,
+        args.fetch_credentials,
+    )
+    .await?;
+    let mut app = App::new(
+        config,
+        session,
+        args.query(),
+        args.store_credentials,
+        args.tick_rate,
+        args.frame_rate,
+    )?;
     app.run().await?;
     Ok(())
 }
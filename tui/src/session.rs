@@ -0,0 +1,86 @@
+use client_core::{PortalClient, Session, login::Login};
+use color_eyre::Result;
+use tracing::{info, warn};
+
+use crate::{
+    cli::SessionCommand,
+    config::{Config, get_session_path},
+};
+
+/// Picks the session to start with: a fresh login if asked for, otherwise the saved session,
+/// then the `ENTAB_*` variables, and finally an interactive login.
+pub async fn load_or_login(
+    config: &Config,
+    login: bool,
+    store_credentials: bool,
+    fetch_credentials: bool,
+) -> Result<Session> {
+    let path = get_session_path();
+    if !login {
+        match Session::load(&path) {
+            Ok(Some(session)) if !session.is_expired() => return Ok(session),
+            Ok(Some(_)) => info!("Saved session has expired"),
+            Ok(None) => {}
+            Err(err) => warn!("Ignoring saved session: {err:?}"),
+        }
+        if let Ok(session) = Session::from_env() {
+            return Ok(session);
+        }
+    }
+
+    let session = Login::login(&config.portal, store_credentials, fetch_credentials).await?;
+    session.save(&path)?;
+    Ok(session)
+}
+
+pub async fn run(command: SessionCommand, config: &Config) -> Result<()> {
+    let path = get_session_path();
+    match command {
+        SessionCommand::Show => {
+            let Some(session) = Session::load(&path)? else {
+                println!("No saved session at {}", path.display());
+                return Ok(());
+            };
+            let format_date = |date: Option<chrono::DateTime<chrono::Utc>>| {
+                date.map(|date| date.to_rfc2822())
+                    .unwrap_or_else(|| "unknown".to_string())
+            };
+            println!("Session file:      {}", path.display());
+            println!("ASP.NET_SessionId: {}", redact(&session.session_id));
+            println!(".ASPXAUTH:         {}", redact(&session.aspxauth));
+            println!("Created:           {}", format_date(session.created_at));
+            println!(
+                "Expires:           {}{}",
+                format_date(session.expires_at),
+                if session.is_expired() {
+                    " (expired)"
+                } else {
+                    ""
+                }
+            );
+        }
+        SessionCommand::Validate => {
+            let Some(session) = Session::load(&path)? else {
+                println!("No saved session at {}", path.display());
+                return Ok(());
+            };
+            let client = PortalClient::new(config.portal.clone(), session)?;
+            if client.validate_session().await? {
+                println!("The saved session is valid");
+            } else {
+                println!("The saved session has expired, run with `--login` to log in again");
+            }
+        }
+        SessionCommand::Clear => {
+            Session::clear(&path)?;
+            println!("Cleared the saved session");
+        }
+    }
+    Ok(())
+}
+
+/// Shows only the start of a cookie value.
+fn redact(value: &str) -> String {
+    let visible = value.chars().take(8).collect::<String>();
+    format!("{visible}…")
+}
@@ -0,0 +1,72 @@
+use client_core::{AssignmentType, Cache, PortalClient, SyncState};
+use color_eyre::Result;
+
+/// Fetches the listings of `types` (all of them if empty) and prints what came in since the
+/// last sync.
+pub async fn run(
+    client: &PortalClient,
+    cache: &Cache,
+    types: Vec<AssignmentType>,
+    check_details: bool,
+) -> Result<()> {
+    let path = cache.sync_state_path("cli");
+    let mut state = SyncState::load(&path)?;
+    let types = if types.is_empty() {
+        vec![AssignmentType::Circular, AssignmentType::Homework]
+    } else {
+        types
+    };
+
+    for type_ in types {
+        let label = match type_ {
+            AssignmentType::Circular => "circulars",
+            AssignmentType::Homework => "homework",
+        };
+        let Some(last_sync) = state.synced_at(type_) else {
+            let diff = client.sync(type_, &mut state, cache, check_details).await?;
+            println!(
+                "Recorded {} {label}, new ones will be listed from the next sync",
+                diff.new.len()
+            );
+            continue;
+        };
+
+        let diff = client.sync(type_, &mut state, cache, check_details).await?;
+        let since = last_sync
+            .with_timezone(&chrono::Local)
+            .format("%d %b %H:%M");
+        if diff.is_empty() {
+            println!("No new {label} since {since}");
+            continue;
+        }
+        if !diff.new.is_empty() {
+            println!("New {label} since {since}:");
+            for assignment in &diff.new {
+                println!(
+                    "  {}  {}  {}",
+                    assignment.date, assignment.subject, assignment.name
+                );
+            }
+        }
+        if !diff.changed.is_empty() {
+            println!("Updated {label}:");
+            for assignment in &diff.changed {
+                println!(
+                    "  {}  {}  {}",
+                    assignment.date, assignment.subject, assignment.name
+                );
+            }
+        }
+        if !diff.removed.is_empty() {
+            println!("No longer listed {label}:");
+            for assignment in &diff.removed {
+                println!(
+                    "  {}  {}  {}",
+                    assignment.date, assignment.subject, assignment.name
+                );
+            }
+        }
+    }
+    state.save(&path)?;
+    Ok(())
+}
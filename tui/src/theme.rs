@@ -0,0 +1,224 @@
+//! Built-in colour themes. Components look styles up by name (`list.selected`,
+//! `details.border`, …) in [`Styles`](crate::config::Styles), which starts from the theme picked
+//! in the config and is overridden key by key by its `styles` section.
+
+use std::collections::HashMap;
+
+use ratatui::style::{
+    Color, Modifier, Style, Stylize,
+    palette::tailwind::{
+        AMBER, BLUE, CYAN, EMERALD, GREEN, LIME, ORANGE, RED, ROSE, SKY, SLATE, TEAL, VIOLET,
+        YELLOW,
+    },
+};
+use serde::Deserialize;
+
+/// Colours for subjects in the assignment list, picked by `list.subject.N`.
+pub const SUBJECT_STYLES: usize = 8;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+}
+
+impl ThemeName {
+    /// Every style key with its value in this theme.
+    pub fn styles(self) -> HashMap<String, Style> {
+        let styles = match self {
+            ThemeName::Dark => dark(),
+            ThemeName::Light => light(),
+            ThemeName::HighContrast => high_contrast(),
+        };
+        styles.into_iter().collect()
+    }
+}
+
+fn subjects(colors: [Color; SUBJECT_STYLES]) -> Vec<(String, Style)> {
+    colors
+        .into_iter()
+        .enumerate()
+        .map(|(index, color)| (format!("list.subject.{index}"), Style::new().fg(color)))
+        .collect()
+}
+
+fn dark() -> Vec<(String, Style)> {
+    let selected = Style::new().bg(SLATE.c800).bold();
+    let mut styles = [
+        ("home.selected", selected),
+        ("list.selected", selected),
+        ("list.border", Style::new().fg(Color::Yellow)),
+        ("list.border.search", Style::new().fg(Color::Yellow)),
+        ("list.border.filter", Style::new().fg(Color::Yellow)),
+        ("list.muted", Style::new().fg(SLATE.c500)),
+        ("list.loading", Style::new().fg(Color::Yellow)),
+        ("list.offline", Style::new().fg(Color::Red).bold()),
+        ("list.new", Style::new().bold()),
+        ("details.border", Style::new().fg(SLATE.c500)),
+        ("details.label", Style::new().fg(SLATE.c400)),
+        ("details.link", Style::new().fg(BLUE.c400).underlined()),
+        ("details.added", Style::new().fg(GREEN.c400)),
+        ("details.removed", Style::new().fg(RED.c400)),
+        ("details.revised", Style::new().fg(YELLOW.c400).bold()),
+        ("popup.selected", selected),
+        ("popup.checked", Style::new().fg(Color::Green)),
+        ("popup.unchecked", Style::new().fg(Color::White).dim()),
+        ("popup.item", Style::new().dim()),
+        ("popup.item.checked", Style::new().bold()),
+        ("popup.progress", Style::new().fg(Color::Cyan)),
+        ("popup.saved", Style::new().fg(Color::Green)),
+        ("popup.failed", Style::new().fg(Color::Red)),
+        ("popup.cancelled", Style::new().fg(Color::Yellow)),
+        ("popup.muted", Style::new().dim()),
+        ("help.key", Style::new().fg(Color::Yellow)),
+        ("status.info", Style::new().fg(Color::Green)),
+        ("status.warning", Style::new().fg(Color::Yellow)),
+        ("status.error", Style::new().fg(Color::Red)),
+        ("status.time", Style::new().dim()),
+    ]
+    .map(|(key, style)| (key.to_string(), style))
+    .to_vec();
+    styles.extend(subjects([
+        SKY.c400,
+        EMERALD.c400,
+        AMBER.c400,
+        ROSE.c400,
+        VIOLET.c400,
+        LIME.c400,
+        ORANGE.c400,
+        TEAL.c400,
+    ]));
+    styles
+}
+
+/// For terminals with a light background: darker shades, and a pale selection.
+fn light() -> Vec<(String, Style)> {
+    let selected = Style::new().bg(SLATE.c200).bold();
+    let mut styles = [
+        ("home.selected", selected),
+        ("list.selected", selected),
+        ("list.border", Style::new().fg(BLUE.c600)),
+        ("list.border.search", Style::new().fg(BLUE.c600)),
+        ("list.border.filter", Style::new().fg(BLUE.c600)),
+        ("list.muted", Style::new().fg(SLATE.c500)),
+        ("list.loading", Style::new().fg(AMBER.c700)),
+        ("list.offline", Style::new().fg(RED.c600).bold()),
+        ("list.new", Style::new().bold()),
+        ("details.border", Style::new().fg(SLATE.c400)),
+        ("details.label", Style::new().fg(SLATE.c600)),
+        ("details.link", Style::new().fg(BLUE.c700).underlined()),
+        ("details.added", Style::new().fg(GREEN.c700)),
+        ("details.removed", Style::new().fg(RED.c700)),
+        ("details.revised", Style::new().fg(AMBER.c700).bold()),
+        ("popup.selected", selected),
+        ("popup.checked", Style::new().fg(GREEN.c700)),
+        ("popup.unchecked", Style::new().fg(SLATE.c400)),
+        ("popup.item", Style::new().fg(SLATE.c600)),
+        ("popup.item.checked", Style::new().bold()),
+        ("popup.progress", Style::new().fg(CYAN.c700)),
+        ("popup.saved", Style::new().fg(GREEN.c700)),
+        ("popup.failed", Style::new().fg(RED.c700)),
+        ("popup.cancelled", Style::new().fg(AMBER.c700)),
+        ("popup.muted", Style::new().fg(SLATE.c500)),
+        ("help.key", Style::new().fg(BLUE.c700)),
+        ("status.info", Style::new().fg(GREEN.c700)),
+        ("status.warning", Style::new().fg(AMBER.c700)),
+        ("status.error", Style::new().fg(RED.c700)),
+        ("status.time", Style::new().fg(SLATE.c500)),
+    ]
+    .map(|(key, style)| (key.to_string(), style))
+    .to_vec();
+    styles.extend(subjects([
+        SKY.c700,
+        EMERALD.c700,
+        AMBER.c700,
+        ROSE.c700,
+        VIOLET.c700,
+        LIME.c700,
+        ORANGE.c700,
+        TEAL.c700,
+    ]));
+    styles
+}
+
+/// Only the basic terminal colours, nothing dimmed, and a reversed selection.
+fn high_contrast() -> Vec<(String, Style)> {
+    let selected = Style::new().add_modifier(Modifier::REVERSED | Modifier::BOLD);
+    let mut styles = [
+        ("home.selected", selected),
+        ("list.selected", selected),
+        ("list.border", Style::new().fg(Color::LightYellow).bold()),
+        (
+            "list.border.search",
+            Style::new().fg(Color::LightYellow).bold(),
+        ),
+        (
+            "list.border.filter",
+            Style::new().fg(Color::LightYellow).bold(),
+        ),
+        ("list.muted", Style::new().fg(Color::White)),
+        ("list.loading", Style::new().fg(Color::LightYellow).bold()),
+        ("list.offline", Style::new().fg(Color::LightRed).bold()),
+        ("list.new", Style::new().bold().underlined()),
+        ("details.border", Style::new().fg(Color::White)),
+        ("details.label", Style::new().fg(Color::White).bold()),
+        (
+            "details.link",
+            Style::new().fg(Color::LightCyan).underlined(),
+        ),
+        ("details.added", Style::new().fg(Color::LightGreen).bold()),
+        ("details.removed", Style::new().fg(Color::LightRed).bold()),
+        (
+            "details.revised",
+            Style::new().fg(Color::LightYellow).bold(),
+        ),
+        ("popup.selected", selected),
+        ("popup.checked", Style::new().fg(Color::LightGreen).bold()),
+        ("popup.unchecked", Style::new().fg(Color::White)),
+        ("popup.item", Style::new()),
+        ("popup.item.checked", Style::new().bold()),
+        ("popup.progress", Style::new().fg(Color::LightCyan)),
+        ("popup.saved", Style::new().fg(Color::LightGreen)),
+        ("popup.failed", Style::new().fg(Color::LightRed).bold()),
+        ("popup.cancelled", Style::new().fg(Color::LightYellow)),
+        ("popup.muted", Style::new()),
+        ("help.key", Style::new().fg(Color::LightYellow).bold()),
+        ("status.info", Style::new().fg(Color::LightGreen).bold()),
+        ("status.warning", Style::new().fg(Color::LightYellow).bold()),
+        ("status.error", Style::new().fg(Color::LightRed).bold()),
+        ("status.time", Style::new()),
+    ]
+    .map(|(key, style)| (key.to_string(), style))
+    .to_vec();
+    styles.extend(subjects([
+        Color::LightCyan,
+        Color::LightGreen,
+        Color::LightYellow,
+        Color::LightMagenta,
+        Color::LightRed,
+        Color::LightBlue,
+        Color::Cyan,
+        Color::White,
+    ]));
+    styles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_themes_define_the_same_keys() {
+        let mut keys = ThemeName::Dark.styles().into_keys().collect::<Vec<_>>();
+        keys.sort();
+        for theme in [ThemeName::Light, ThemeName::HighContrast] {
+            let mut other = theme.styles().into_keys().collect::<Vec<_>>();
+            other.sort();
+            assert_eq!(keys, other, "{theme:?}");
+        }
+        assert!(keys.contains(&format!("list.subject.{}", SUBJECT_STYLES - 1)));
+    }
+}